@@ -0,0 +1,114 @@
+use alloy_chains::Chain;
+use datafeeds::{Oracle, OraclesIndex};
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::{Duration, SystemTime, UNIX_EPOCH}};
+use crate::output::Render;
+
+/// Time a downloaded index is used before downloading it again
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Reference feed index of a chain as stored on disk
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedIndex {
+    fetched_at: u64,
+    oracles: Vec<Oracle>,
+}
+
+/// Result of refreshing the cached index of a chain
+#[derive(Debug, Serialize)]
+pub struct CacheUpdate {
+    pub chain: String,
+    pub feeds: usize,
+    pub path: String,
+}
+
+impl Render for CacheUpdate {
+    fn text(&self) -> String {
+        format!("[{}] {} feeds cached in {}", self.chain, self.feeds, self.path)
+    }
+}
+
+/// On-disk cache of the reference feed indexes, one file per chain id
+#[derive(Debug, Clone)]
+pub struct IndexCache {
+    pub dir: PathBuf,
+    pub ttl: Duration,
+    /// never download, use the cached index whatever its age
+    pub offline: bool,
+}
+
+impl IndexCache {
+    /// Default location of the cache, `~/.cache/chainlink-cli/feeds`
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::cache_dir().map(|d| d.join("chainlink-cli").join("feeds"))
+    }
+
+    pub fn new(dir: PathBuf, ttl: Duration, offline: bool) -> Self {
+        Self { dir, ttl, offline }
+    }
+
+    fn path(&self, chain: Chain) -> PathBuf {
+        self.dir.join(format!("{}.json", chain.id()))
+    }
+
+    fn read(&self, chain: Chain) -> Option<CachedIndex> {
+        let content = std::fs::read_to_string(self.path(chain)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Index of `chain`: from the cache when it is younger than the TTL (or offline),
+    /// downloaded and cached otherwise. A stale cache is used when the download gives nothing
+    pub async fn load(&self, chain: Chain) -> Result<OraclesIndex> {
+        let cached = self.read(chain);
+        if self.offline {
+            return cached
+                .map(|c| OraclesIndex::new(c.oracles))
+                .ok_or(eyre!("No cached feeds for {chain} in {}, run `feeds update` first", self.dir.display()))
+        }
+        if let Some(c) = cached.as_ref() {
+            if now().saturating_sub(c.fetched_at) < self.ttl.as_secs() {
+                return Ok(OraclesIndex::new(c.oracles.clone()))
+            }
+        }
+        let index = OraclesIndex::load_reference_feeds(chain).await;
+        if index.oracles.is_empty() {
+            if let Some(c) = cached {
+                return Ok(OraclesIndex::new(c.oracles))
+            }
+            return Ok(index)
+        }
+        self.write(chain, &index)?;
+        Ok(index)
+    }
+
+    /// Downloads the index of `chain` and replaces the cached one
+    pub async fn update(&self, chain: Chain) -> Result<CacheUpdate> {
+        if self.offline {
+            return Err(eyre!("Cannot update the feeds cache offline"))
+        }
+        let index = OraclesIndex::load_reference_feeds(chain).await;
+        if index.oracles.is_empty() {
+            return Err(eyre!("No reference feeds downloaded for {chain}"))
+        }
+        self.write(chain, &index)?;
+        Ok(CacheUpdate {
+            chain: chain.to_string(),
+            feeds: index.oracles.len(),
+            path: self.path(chain).display().to_string(),
+        })
+    }
+
+    fn write(&self, chain: Chain, index: &OraclesIndex) -> Result<()> {
+        let cached = CachedIndex { fetched_at: now(), oracles: index.oracles.clone() };
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| eyre!("Cannot create cache dir {}: {}", self.dir.display(), e))?;
+        let path = self.path(chain);
+        std::fs::write(&path, serde_json::to_string(&cached)?)
+            .map_err(|e| eyre!("Cannot write cache {}: {}", path.display(), e))
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
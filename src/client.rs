@@ -0,0 +1,368 @@
+use alloy_chains::Chain;
+use alloy_providers::provider::TempProvider;
+use alloy_rpc_types::{BlockId, BlockNumberOrTag};
+use datafeeds::{Oracle, OraclesIndex};
+use eyre::{eyre, Result};
+use std::sync::Arc;
+use crate::constants::{get_sequencer_uptime_feed, L2_CHAINS};
+use crate::functions::datafeeds;
+use crate::functions::datafeeds::Phase;
+use crate::functions::events::EventSource;
+use crate::health::{check_deviation, check_round, round_deviation, FeedHealth, HealthSettings};
+use crate::routes::{derive_price, find_route, DerivedPrice};
+use crate::sequencer::{SequencedRound, SequencerStatus};
+use crate::provider::ChainProvider;
+use crate::verify::{check_oracle, FeedCheck};
+use crate::types::{AnswerStatus, Description, FeedInfo, PairAnswer, Price, RoundAtBlock, RoundData};
+
+/// Reads Chainlink data feeds of a chain.
+/// Owns the provider and the reference index, every method returns typed results and prints nothing
+pub struct FeedsClient {
+    chain: Chain,
+    provider: Arc<ChainProvider>,
+    index: OraclesIndex,
+    /// block every read is made at, latest when `None`
+    block: Option<BlockId>,
+}
+
+impl FeedsClient {
+    /// Creates a client loading the reference feeds of `chain`
+    pub async fn new(chain: Chain, provider: Arc<ChainProvider>) -> Self {
+        let index = OraclesIndex::load_reference_feeds(chain).await;
+        Self::with_index(chain, provider, index)
+    }
+
+    /// Creates a client with an already loaded index
+    pub fn with_index(chain: Chain, provider: Arc<ChainProvider>, index: OraclesIndex) -> Self {
+        Self { chain, provider, index, block: None }
+    }
+
+    /// Pins every read of the client to `block`
+    pub fn at_block(mut self, block: Option<BlockId>) -> Self {
+        self.block = block;
+        self
+    }
+
+    pub fn block(&self) -> Option<BlockId> {
+        self.block
+    }
+
+    pub fn chain(&self) -> Chain {
+        self.chain
+    }
+
+    pub fn provider(&self) -> Arc<ChainProvider> {
+        self.provider.clone()
+    }
+
+    pub fn index(&self) -> &OraclesIndex {
+        &self.index
+    }
+
+    /// Oracle of the pair in the reference index (case insensitive)
+    pub fn oracle(&self, base: &str, quote: &str) -> Result<&Oracle> {
+        self.index
+            .get_oracle(&base.to_uppercase(), &quote.to_uppercase())
+            .ok_or(eyre!("No oracle found for {}/{} in {}", base, quote, self.chain))
+    }
+
+    fn proxy(&self, base: &str, quote: &str) -> Result<(&Oracle, alloy_primitives::Address)> {
+        let oracle = self.oracle(base, quote)?;
+        let proxy = oracle.proxy_address.ok_or(eyre!("Oracle for {}/{} has no proxy address", base, quote))?;
+        Ok((oracle, proxy))
+    }
+
+    fn pair(base: &str, quote: &str) -> String {
+        format!("{}/{}", base.to_uppercase(), quote.to_uppercase())
+    }
+
+    pub fn feed_info(&self, base: &str, quote: &str) -> Result<FeedInfo> {
+        let oracle = self.oracle(base, quote)?;
+        Ok(FeedInfo {
+            chain: self.chain.to_string(),
+            pair: Self::pair(base, quote),
+            name: oracle.name.clone(),
+            proxy_address: oracle.proxy_address,
+            decimals: oracle.decimals,
+        })
+    }
+
+    pub async fn latest_price(&self, base: &str, quote: &str) -> Result<Price> {
+        let (oracle, proxy) = self.proxy(base, quote)?;
+        let raw = datafeeds::get_latest_answer(self.provider(), proxy, self.block).await?;
+        Ok(Price {
+            chain: self.chain.to_string(),
+            pair: Self::pair(base, quote),
+            raw,
+            decimals: oracle.decimals.unwrap_or_default(),
+        })
+    }
+
+    /// Latest answer of the pair, derived through other feeds (at most `max_legs`) when it has no feed
+    pub async fn latest_answer(&self, base: &str, quote: &str, max_legs: usize) -> Result<PairAnswer> {
+        if self.oracle(base, quote).is_err() {
+            let derived = self
+                .cross_price(base, quote, max_legs)
+                .await
+                .map_err(|e| eyre!("No oracle found for {}/{} in {} and no cross rate: {}", base, quote, self.chain, e))?;
+            return Ok(Self::derived_answer(derived))
+        }
+        let price = self.latest_price(base, quote).await?;
+        Ok(PairAnswer {
+            chain: price.chain.clone(),
+            pair: price.pair.clone(),
+            status: AnswerStatus::Ok,
+            price: Some(price),
+            route: None,
+            oldest_updated_at: None,
+        })
+    }
+
+    /// Latest prices of several pairs in a single multicall, in the order of `pairs`.
+    /// Pairs without feed are derived through other feeds (at most `max_legs`) when possible,
+    /// the others are reported with their status
+    pub async fn latest_prices(&self, pairs: &[(String, String)], max_legs: usize) -> Result<Vec<PairAnswer>> {
+        let (bases, quotes) = pairs.iter().cloned().unzip();
+        let mut answers = datafeeds::get_multiple_latest_answer(
+            self.provider(),
+            &self.index,
+            self.chain,
+            bases,
+            quotes,
+            self.block,
+        ).await?;
+        for (answer, (base, quote)) in answers.iter_mut().zip(pairs) {
+            if answer.status != AnswerStatus::NoFeed {
+                continue
+            }
+            if let Ok(derived) = self.cross_price(base, quote, max_legs).await {
+                *answer = Self::derived_answer(derived);
+            }
+        }
+        Ok(answers)
+    }
+
+    fn derived_answer(derived: DerivedPrice) -> PairAnswer {
+        PairAnswer {
+            chain: derived.price.chain.clone(),
+            pair: derived.price.pair.clone(),
+            status: AnswerStatus::Derived,
+            price: Some(derived.price),
+            route: Some(derived.route),
+            oldest_updated_at: Some(derived.oldest_updated_at),
+        }
+    }
+
+    /// Latest round data of several pairs in a single multicall, pairs without feed are skipped
+    pub async fn latest_rounds_data(&self, bases: &[String], quotes: &[String]) -> Result<Vec<RoundData>> {
+        datafeeds::get_multiple_latest_round_data(
+            self.provider(),
+            &self.index,
+            bases.to_vec(),
+            quotes.to_vec(),
+            self.block,
+        ).await
+    }
+
+    pub async fn latest_round_data(&self, base: &str, quote: &str) -> Result<RoundData> {
+        let (oracle, proxy) = self.proxy(base, quote)?;
+        let round = datafeeds::get_latest_round_data(self.provider(), proxy, self.block).await?;
+        Ok(RoundData::new(&Self::pair(base, quote), &round, oracle.decimals.unwrap_or_default()))
+    }
+
+    /// Latest round of the pair with the status of the L2 sequencer, when the chain has one
+    pub async fn latest_round_data_sequenced(&self, base: &str, quote: &str, grace_period: u64) -> Result<SequencedRound> {
+        let round = self.latest_round_data(base, quote).await?;
+        // the round is still returned when the sequencer cannot be read, with the status unknown
+        let (sequencer, sequencer_error) = match self.sequencer_status(grace_period).await {
+            Ok(None) if L2_CHAINS.contains(&self.chain.id()) => (None, Some(format!("no sequencer uptime feed known for {}", self.chain))),
+            Ok(sequencer) => (sequencer, None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        Ok(SequencedRound { round, sequencer, sequencer_error })
+    }
+
+    /// Time of the pinned block, the clock when reading at the latest block
+    async fn now(&self) -> Result<u64> {
+        let block = match self.block {
+            None => return Ok(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs()),
+            Some(BlockId::Number(number)) => self.provider.get_block_by_number(number, false).await?,
+            Some(BlockId::Hash(hash)) => self.provider.get_block_by_hash(hash.block_hash, false).await?,
+        };
+        let block = block.ok_or(eyre!("Block {:?} not found", self.block))?;
+        Ok(block.header.timestamp.to::<u64>())
+    }
+
+    /// Status of the sequencer uptime feed, `None` on chains without sequencer feed
+    pub async fn sequencer_status(&self, grace_period: u64) -> Result<Option<SequencerStatus>> {
+        let Some(feed) = get_sequencer_uptime_feed(self.chain.id()) else {
+            return Ok(None)
+        };
+        let feed = feed.parse::<alloy_primitives::Address>()?;
+        let round = datafeeds::get_latest_round_data(self.provider(), feed, self.block).await?;
+        let now = self.now().await?;
+        Ok(Some(SequencerStatus::new(&self.chain.to_string(), feed, &round, now, grace_period)))
+    }
+
+    pub async fn round_data(&self, base: &str, quote: &str, round_id: u128) -> Result<RoundData> {
+        let (oracle, proxy) = self.proxy(base, quote)?;
+        let round = datafeeds::get_round_data(self.provider(), proxy, round_id, self.block).await?;
+        Ok(RoundData::new(&Self::pair(base, quote), &round, oracle.decimals.unwrap_or_default()))
+    }
+
+    /// Round data for several rounds in a single multicall, rounds that cannot be read are skipped
+    pub async fn rounds_data(&self, base: &str, quote: &str, round_ids: &[u128]) -> Result<Vec<RoundData>> {
+        let (oracle, proxy) = self.proxy(base, quote)?;
+        let rounds = datafeeds::get_multiple_round_data(self.provider(), proxy, round_ids.to_vec(), self.block).await?;
+        Ok(self.to_round_data(base, quote, oracle, &rounds))
+    }
+
+    pub async fn description(&self, base: &str, quote: &str) -> Result<Description> {
+        datafeeds::get_description(self.provider(), self.oracle(base, quote)?, self.block).await
+    }
+
+    pub async fn phases(&self, base: &str, quote: &str) -> Result<Vec<Phase>> {
+        datafeeds::get_phases(self.provider(), self.oracle(base, quote)?, self.block).await
+    }
+
+    /// Round that was current at `timestamp`, `None` if the feed had no answer yet
+    pub async fn price_at(&self, base: &str, quote: &str, timestamp: u64) -> Result<Option<RoundData>> {
+        let oracle = self.oracle(base, quote)?;
+        let round = datafeeds::find_round_id_timestamp(self.provider(), oracle, timestamp, self.block).await?;
+        Ok(round.map(|r| RoundData::new(&Self::pair(base, quote), &r, oracle.decimals.unwrap_or_default())))
+    }
+
+    /// Every round updated between `from` and `to`, read in multicalls of `chunk_size` rounds
+    pub async fn history(&self, base: &str, quote: &str, from: u64, to: u64, chunk_size: usize) -> Result<Vec<RoundData>> {
+        let oracle = self.oracle(base, quote)?;
+        let rounds = datafeeds::get_history(self.provider(), oracle, from, to, chunk_size, self.block).await?;
+        Ok(self.to_round_data(base, quote, oracle, &rounds))
+    }
+
+    /// Price of the pair composed through intermediate feeds (at most `max_legs`) when there is no direct feed
+    pub async fn cross_price(&self, base: &str, quote: &str, max_legs: usize) -> Result<DerivedPrice> {
+        let route = find_route(&self.index, base, quote, max_legs)
+            .ok_or(eyre!("No route of up to {} feeds found for {}/{} in {}", max_legs, base, quote, self.chain))?;
+        let bases: Vec<String> = route.iter().map(|l| l.base.clone()).collect();
+        let quotes: Vec<String> = route.iter().map(|l| l.quote.clone()).collect();
+        let rounds = self.latest_rounds_data(&bases, &quotes).await?;
+        if rounds.len() != route.len() {
+            return Err(eyre!("Cannot read every feed of the route for {}/{}", base, quote))
+        }
+        let decimals = route
+            .iter()
+            .map(|l| self.oracle(&l.base, &l.quote).map(|o| o.decimals.unwrap_or_default()))
+            .collect::<Result<Vec<u8>>>()?;
+        derive_price(&self.chain.to_string(), base, quote, &route, rounds, &decimals)
+    }
+
+    /// Answer and round that were live at `block`
+    pub async fn price_at_block(&self, base: &str, quote: &str, block: u64) -> Result<RoundAtBlock> {
+        let (oracle, proxy) = self.proxy(base, quote)?;
+        let round = datafeeds::get_latest_round_data(self.provider(), proxy, Some(block.into())).await?;
+        Ok(RoundAtBlock {
+            block,
+            round: RoundData::new(&Self::pair(base, quote), &round, oracle.decimals.unwrap_or_default()),
+        })
+    }
+
+    /// Block in which `round_id` was first reported, searched from `from_block` (default genesis)
+    /// up to the pinned block of the client (default latest)
+    pub async fn block_of_round(&self, base: &str, quote: &str, round_id: u128, from_block: Option<u64>) -> Result<Option<RoundAtBlock>> {
+        let (oracle, proxy) = self.proxy(base, quote)?;
+        let to_block = match self.block {
+            Some(BlockId::Number(BlockNumberOrTag::Number(n))) => n.to::<u64>(),
+            _ => self.provider.get_block_number().await?.to::<u64>(),
+        };
+        let found = datafeeds::find_block_of_round(self.provider(), proxy, round_id, from_block.unwrap_or(0), to_block).await?;
+        Ok(found.map(|(block, round)| RoundAtBlock {
+            block,
+            round: RoundData::new(&Self::pair(base, quote), &round, oracle.decimals.unwrap_or_default()),
+        }))
+    }
+
+    /// Checks the latest round of the pair for staleness, incomplete rounds and non positive answers
+    pub async fn check_feed(&self, base: &str, quote: &str, settings: &HealthSettings) -> Result<FeedHealth> {
+        let (oracle, proxy) = self.proxy(base, quote)?;
+        let round = datafeeds::get_latest_round_data(self.provider(), proxy, self.block).await?;
+        let now = self.now().await?;
+        let mut issues = check_round(&round, oracle.heartbeat, now, settings);
+        // the previous round of the same phase, the first round of a phase has none to compare with
+        let deviation = match datafeeds::split_round_id(round.roundId) {
+            (_, aggregator_round) if aggregator_round > 1 => {
+                let previous = datafeeds::get_round_data(self.provider(), proxy, round.roundId - 1, self.block).await?;
+                round_deviation(&previous, &round)
+            },
+            _ => None,
+        };
+        if let (Some(d), Some(threshold)) = (&deviation, oracle.threshold) {
+            issues.extend(check_deviation(d, threshold, oracle.heartbeat, settings));
+        }
+        Ok(FeedHealth {
+            chain: self.chain.to_string(),
+            round: RoundData::new(&Self::pair(base, quote), &round, oracle.decimals.unwrap_or_default()),
+            age: now.saturating_sub(round.updatedAt.to::<u64>()),
+            heartbeat: oracle.heartbeat,
+            threshold: oracle.threshold,
+            deviation,
+            healthy: issues.is_empty(),
+            issues,
+        })
+    }
+
+    /// Checks every feed of the index against the description, decimals and aggregator of its proxy,
+    /// reading `chunk_size` proxies per multicall
+    pub async fn verify_feeds(&self, chunk_size: usize) -> Result<Vec<FeedCheck>> {
+        let proxies: Vec<alloy_primitives::Address> = self.index.oracles.iter().filter_map(|o| o.proxy_address).collect();
+        let metadata = datafeeds::get_proxies_metadata(self.provider(), proxies, chunk_size, self.block).await?;
+        let mut metadata = metadata.iter();
+        let chain = self.chain.to_string();
+        Ok(self.index
+            .oracles
+            .iter()
+            .map(|o| {
+                let m = o.proxy_address.and_then(|_| metadata.next());
+                check_oracle(&chain, o, m)
+            })
+            .collect())
+    }
+
+    /// Source to decode the aggregator events of the pair
+    pub fn event_source(&self, base: &str, quote: &str) -> Result<EventSource> {
+        let (oracle, proxy) = self.proxy(base, quote)?;
+        Ok(EventSource {
+            pair: Self::pair(base, quote),
+            proxy,
+            decimals: oracle.decimals.unwrap_or_default(),
+        })
+    }
+
+    fn to_round_data(&self, base: &str, quote: &str, oracle: &Oracle, rounds: &[datafeeds::GetRoundDataReturn]) -> Vec<RoundData> {
+        let pair = Self::pair(base, quote);
+        rounds
+            .iter()
+            .map(|r| RoundData::new(&pair, r, oracle.decimals.unwrap_or_default()))
+            .collect()
+    }
+}
+
+/// Pairs requested from lists of bases and quotes:
+/// a single quote is used for every base, lists of the same length are zipped,
+/// and every combination is taken with `matrix` or when the lengths differ (same asset pairs excluded)
+pub fn expand_pairs(bases: &[String], quotes: &[String], matrix: bool) -> Result<Vec<(String, String)>> {
+    if bases.is_empty() || quotes.is_empty() {
+        return Err(eyre!("At least one base and one quote are needed"))
+    }
+    let pairs = if quotes.len() == 1 {
+        bases.iter().map(|b| (b.clone(), quotes[0].clone())).collect()
+    } else if bases.len() == quotes.len() && !matrix {
+        bases.iter().cloned().zip(quotes.iter().cloned()).collect()
+    } else {
+        bases
+            .iter()
+            .flat_map(|b| quotes.iter().map(move |q| (b.clone(), q.clone())))
+            .filter(|(b, q)| !b.eq_ignore_ascii_case(q))
+            .collect()
+    };
+    Ok(pairs)
+}
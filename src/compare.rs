@@ -0,0 +1,99 @@
+use serde::Serialize;
+use crate::output::Render;
+use crate::types::{as_opt_string, RoundData};
+
+/// Latest round of a pair on one chain, compared with the other chains
+#[derive(Debug, Serialize)]
+pub struct ChainQuote {
+    pub chain: String,
+    pub pair: String,
+    pub price: Option<String>,
+    #[serde(serialize_with = "as_opt_string")]
+    pub round_id: Option<u128>,
+    pub updated_at: Option<u64>,
+    /// seconds behind the most recently updated chain
+    pub lag: Option<u64>,
+    /// median price over the chains that answered
+    pub median: Option<String>,
+    /// deviation from the median, in percent
+    pub deviation_pct: Option<f64>,
+    pub error: Option<String>,
+}
+
+impl Render for ChainQuote {
+    fn text(&self) -> String {
+        match (&self.price, &self.error) {
+            (Some(price), _) => format!("[{}] {} = {} updated at {} ({}s behind), {:+.4}% from median {}",
+                self.chain,
+                self.pair,
+                price,
+                self.updated_at.unwrap_or_default(),
+                self.lag.unwrap_or_default(),
+                self.deviation_pct.unwrap_or_default(),
+                self.median.as_deref().unwrap_or("unknown"),
+            ),
+            (None, error) => format!("[{}] {}: {}", self.chain, self.pair, error.as_deref().unwrap_or("no answer")),
+        }
+    }
+}
+
+/// Compares the latest round of the same pair read on several chains
+pub fn compare_chains(pair: &str, results: Vec<(String, eyre::Result<RoundData>)>) -> Vec<ChainQuote> {
+    let prices: Vec<f64> = results
+        .iter()
+        .filter_map(|(_, r)| r.as_ref().ok())
+        .filter_map(|r| r.price.parse().ok())
+        .collect();
+    let median = median(prices);
+    let newest = results
+        .iter()
+        .filter_map(|(_, r)| r.as_ref().ok())
+        .map(|r| r.updated_at)
+        .max();
+    results
+        .into_iter()
+        .map(|(chain, result)| match result {
+            Ok(round) => {
+                let deviation_pct = match (median, round.price.parse::<f64>()) {
+                    (Some(m), Ok(p)) if m != 0.0 => Some((p - m) / m.abs() * 100.0),
+                    _ => None,
+                };
+                ChainQuote {
+                    chain,
+                    pair: round.pair,
+                    price: Some(round.price),
+                    round_id: Some(round.round_id),
+                    updated_at: Some(round.updated_at),
+                    lag: newest.map(|n| n.saturating_sub(round.updated_at)),
+                    median: median.map(|m| m.to_string()),
+                    deviation_pct,
+                    error: None,
+                }
+            },
+            Err(e) => ChainQuote {
+                chain,
+                pair: pair.to_string(),
+                price: None,
+                round_id: None,
+                updated_at: None,
+                lag: None,
+                median: median.map(|m| m.to_string()),
+                deviation_pct: None,
+                error: Some(e.to_string()),
+            },
+        })
+        .collect()
+}
+
+fn median(mut values: Vec<f64>) -> Option<f64> {
+    if values.is_empty() {
+        return None
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mid = values.len() / 2;
+    if values.len() % 2 == 0 {
+        Some((values[mid - 1] + values[mid]) / 2.0)
+    } else {
+        Some(values[mid])
+    }
+}
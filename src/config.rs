@@ -0,0 +1,186 @@
+use alloy_chains::Chain;
+use eyre::{eyre, Result};
+use serde::Deserialize;
+use std::{collections::HashMap, path::{Path, PathBuf}, str::FromStr, time::Duration};
+use crate::cache::{IndexCache, DEFAULT_CACHE_TTL};
+use crate::constants::get_provider_rpc_url;
+
+/// Environment variable pointing to a config file, used when `--config` is not given
+pub const CONFIG_PATH_ENV: &str = "CHAINLINK_CLI_CONFIG";
+
+/// User configuration, read from `~/.config/chainlink-cli/config.toml`
+///
+/// ```toml
+/// [rpc]
+/// mainnet = "https://eth-mainnet.g.alchemy.com/v2/${ALCHEMY_KEY}"
+/// arbitrum = ["https://arb1.arbitrum.io/rpc", "https://arbitrum.llamarpc.com"]
+/// 31337 = "http://127.0.0.1:8545"
+///
+/// [cache]
+/// ttl = 86400
+/// ```
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    /// RPC URLs by chain name or chain id
+    #[serde(default)]
+    pub rpc: HashMap<String, RpcUrls>,
+    #[serde(default)]
+    pub cache: CacheConfig,
+}
+
+/// Cache of the reference feed indexes
+#[derive(Debug, Default, Deserialize)]
+pub struct CacheConfig {
+    /// defaults to `~/.cache/chainlink-cli/feeds`
+    pub dir: Option<PathBuf>,
+    /// seconds a downloaded index is used
+    pub ttl: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum RpcUrls {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl RpcUrls {
+    pub fn to_vec(&self) -> Vec<String> {
+        match self {
+            RpcUrls::One(url) => vec![url.clone()],
+            RpcUrls::Many(urls) => urls.clone(),
+        }
+    }
+}
+
+impl Config {
+    /// Default location of the config file
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join("chainlink-cli").join("config.toml"))
+    }
+
+    /// Loads the config from `path`, `CHAINLINK_CLI_CONFIG` or the default location.
+    /// A missing file at the default location gives an empty config
+    pub fn load(path: Option<&Path>) -> Result<Config> {
+        let explicit = path
+            .map(Path::to_path_buf)
+            .or(std::env::var(CONFIG_PATH_ENV).ok().map(PathBuf::from));
+        let path = match explicit {
+            Some(p) => p,
+            None => match Self::default_path() {
+                Some(p) if p.exists() => p,
+                _ => return Ok(Config::default()),
+            },
+        };
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| eyre!("Cannot read config {}: {}", path.display(), e))?;
+        toml::from_str(&content).map_err(|e| eyre!("Invalid config {}: {}", path.display(), e))
+    }
+
+    /// RPC URLs configured for `chain`, with environment variables interpolated
+    pub fn rpc_urls(&self, chain: Chain) -> Result<Vec<String>> {
+        let mut urls = Vec::new();
+        for (key, value) in self.rpc.iter() {
+            let matches = Chain::from_str(key).map(|c| c.id() == chain.id()).unwrap_or(false);
+            if matches {
+                for url in value.to_vec() {
+                    urls.push(interpolate_env(&url)?);
+                }
+            }
+        }
+        Ok(urls)
+    }
+
+    /// Cache of the feed indexes described by the config
+    pub fn index_cache(&self, offline: bool) -> Result<IndexCache> {
+        let dir = self
+            .cache
+            .dir
+            .clone()
+            .or(IndexCache::default_dir())
+            .ok_or(eyre!("No cache directory found, set one in the config file"))?;
+        let ttl = self.cache.ttl.map(Duration::from_secs).unwrap_or(DEFAULT_CACHE_TTL);
+        Ok(IndexCache::new(dir, ttl, offline))
+    }
+
+    /// URLs to reach `chain`: the `--rpc-url` overrides, then the config file,
+    /// and last the Infura endpoints when `RPC_URL_ID` is set
+    pub fn resolve_rpc_urls(&self, chain: Chain, rpc_urls: &[String]) -> Result<Vec<String>> {
+        if !rpc_urls.is_empty() {
+            return rpc_urls.iter().map(|url| interpolate_env(url)).collect()
+        }
+        let urls = self.rpc_urls(chain)?;
+        if !urls.is_empty() {
+            return Ok(urls)
+        }
+        match std::env::var("RPC_URL_ID") {
+            Ok(rpc_url_id) => Ok(vec![get_provider_rpc_url(chain.id(), &rpc_url_id)?]),
+            Err(_) => Err(eyre!("No RPC URL configured for {chain}, add it to the config file or use --rpc-url")),
+        }
+    }
+}
+
+/// Replaces `${VAR}` with the value of the environment variable
+pub fn interpolate_env(input: &str) -> Result<String> {
+    let mut output = String::new();
+    let mut rest = input;
+    while let Some(start) = rest.find("${") {
+        output.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or(eyre!("Unclosed variable in {input}"))?;
+        let name = &rest[start + 2..start + end];
+        let value = std::env::var(name).map_err(|_| eyre!("Environment variable {name} is not set (used in {input})"))?;
+        output.push_str(&value);
+        rest = &rest[start + end + 1..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interpolate_variables() {
+        std::env::set_var("CHAINLINK_CLI_TEST_KEY", "abc");
+        assert_eq!(interpolate_env("https://rpc.example/${CHAINLINK_CLI_TEST_KEY}/v2").unwrap(), "https://rpc.example/abc/v2");
+        assert_eq!(interpolate_env("${CHAINLINK_CLI_TEST_KEY}${CHAINLINK_CLI_TEST_KEY}").unwrap(), "abcabc");
+        assert_eq!(interpolate_env("https://rpc.example/$KEY/{x}").unwrap(), "https://rpc.example/$KEY/{x}");
+    }
+
+    #[test]
+    fn interpolate_missing_variable() {
+        std::env::remove_var("CHAINLINK_CLI_TEST_MISSING");
+        let error = interpolate_env("https://rpc.example/${CHAINLINK_CLI_TEST_MISSING}").unwrap_err();
+        assert!(error.to_string().contains("CHAINLINK_CLI_TEST_MISSING"));
+    }
+
+    #[test]
+    fn interpolate_unterminated_variable() {
+        assert!(interpolate_env("https://rpc.example/${CHAINLINK_CLI_TEST_KEY").is_err());
+        assert!(interpolate_env("https://rpc.example/${").is_err());
+    }
+
+    #[test]
+    fn rpc_url_precedence() {
+        let config: Config = toml::from_str(r#"
+            [rpc]
+            mainnet = "https://config.example/${CHAINLINK_CLI_TEST_CONFIG_KEY}"
+            42161 = ["https://a.example", "https://b.example"]
+        "#).unwrap();
+        std::env::set_var("CHAINLINK_CLI_TEST_CONFIG_KEY", "key");
+        std::env::set_var("RPC_URL_ID", "infura-id");
+        // --rpc-url first
+        let flag = vec!["https://flag.example".to_string()];
+        assert_eq!(config.resolve_rpc_urls(Chain::mainnet(), &flag).unwrap(), vec!["https://flag.example"]);
+        // then the config file
+        assert_eq!(config.resolve_rpc_urls(Chain::mainnet(), &[]).unwrap(), vec!["https://config.example/key"]);
+        assert_eq!(config.resolve_rpc_urls(Chain::from_id(42161), &[]).unwrap(), vec!["https://a.example", "https://b.example"]);
+        // then Infura
+        assert_eq!(config.resolve_rpc_urls(Chain::from_id(137), &[]).unwrap(), vec!["https://polygon-mainnet.infura.io/v3/infura-id"]);
+        std::env::remove_var("RPC_URL_ID");
+        assert!(config.resolve_rpc_urls(Chain::from_id(137), &[]).is_err());
+    }
+}
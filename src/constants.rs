@@ -6,6 +6,25 @@ https://docs.chain.link/data-feeds/price-feeds/addresses?network=ethereum&page=1
 */
 pub const MULTICALL3: &str = "0xcA11bde05977b3631167028862bE2a173976CA11";
 
+/// Assets tried as intermediate steps when deriving a cross rate
+pub const CROSS_RATE_INTERMEDIATES: [&str; 4] = ["USD", "ETH", "BTC", "EUR"];
+
+/// Feeds composed at most when a pair without feed falls back to a cross rate
+pub const DEFAULT_MAX_LEGS: usize = 3;
+
+/// Chains with reference feeds, queried by `--all-chains`
+pub const REFERENCE_FEED_CHAINS: [&str; 8] = [
+    "mainnet",
+    "arbitrum",
+    "optimism",
+    "base",
+    "polygon",
+    "avalanche",
+    "bsc",
+    "scroll",
+];
+
+/// Infura endpoints, used when no RPC URL is configured for the chain and `RPC_URL_ID` is set
 pub fn get_provider_rpc_url(chain: u64, rpc_url_id: &str) -> Result<String> {
     match chain {
         1 => Ok(format!("https://mainnet.infura.io/v3/{}", rpc_url_id)),
@@ -22,4 +41,47 @@ pub fn get_provider_rpc_url(chain: u64, rpc_url_id: &str) -> Result<String> {
         84_531 => Ok(String::from("https://base-goerli.blockpi.network/v1/rpc/public")),
         _ => Err(eyre::eyre!("Chain has no RPC URL")) 
     }
-}
\ No newline at end of file
+}
+
+/// Seconds after a sequencer restart during which L2 prices are not trusted
+pub const DEFAULT_SEQUENCER_GRACE: u64 = 3600;
+
+/// Sequencer uptime feed of an L2 chain, answer is 0 while the sequencer is up and 1 while it is down
+pub fn get_sequencer_uptime_feed(chain: u64) -> Option<&'static str> {
+    match chain {
+        42_161 => Some("0xFdB631F5EE196F0ed6FAa767959853A9F217697D"),
+        10 => Some("0x371EAD81c9102C9BF4874A9075FFFf170F2Ee389"),
+        8_453 => Some("0xBCF85224fc0756B9Fa45aA7892530B47e10b6433"),
+        1_088 => Some("0x58218ea7422255EBE94e56b504035a784b7AA204"),
+        534_352 => Some("0x45c2b8C204568A03Dc7A2E32B71D67Fe97F908A9"),
+        324 => Some("0x0E6AC8B967393dcD3D36677c126976157F993940"),
+        _ => None
+    }
+}
+
+/// L2 chains whose prices depend on a sequencer, with or without a known uptime feed:
+/// Arbitrum, Optimism, Base, Metis, Scroll, zkSync, Linea, Polygon zkEVM, Mantle and Blast
+pub const L2_CHAINS: [u64; 10] = [42_161, 10, 8_453, 1_088, 534_352, 324, 59_144, 1_101, 5_000, 81_457];
+
+/// LINK token of the chains with CCIP, used to pay fees
+pub fn get_link_token(chain: u64) -> Option<&'static str> {
+    match chain {
+        1 => Some("0x514910771AF9Ca656af840dff83E8264EcF986CA"),
+        11_155_111 => Some("0x779877A7B0D9E8603169DdbD7836e478b4624789"),
+        137 => Some("0xb0897686c545045aFc77CF20eC7A532E3120E0F1"),
+        80_001 => Some("0x326C977E6efc84E512bB9C30f76E30c160eD06FB"),
+        10 => Some("0x350a791Bfc2C21F9Ed5d10980Dad2e2638ffa7f6"),
+        42_161 => Some("0xf97f4df75117a78c1A5a0DBb814Af92458539FB4"),
+        8_453 => Some("0x88Fb150BDc53A65fe94Dea0c9BA0a6dAf8C6e196"),
+        43_114 => Some("0x5947BB275c521040051D82396192181b413227A3"),
+        43_113 => Some("0x0b9d5D9136855f6FEc3c0993feE6E9CE8a297846"),
+        56 => Some("0x404460C6A5EdE2D891e8297795264fDe62ADBB75"),
+        _ => None
+    }
+}
+
+/// Seconds searched back for a CCIP message id when no start block is given
+pub const CCIP_SEARCH_WINDOW: u64 = 7 * 24 * 60 * 60;
+
+/// Blocks read by each `eth_getLogs` request when searching CCIP logs
+pub const CCIP_LOG_PAGE: u64 = 5_000;
@@ -0,0 +1,524 @@
+use alloy_sol_types::{sol, SolCall, SolEvent, SolValue};
+use alloy_primitives::{Address, Bytes, B256, U256, U64};
+use alloy_providers::provider::TempProvider;
+use alloy_rpc_types::{BlockId, BlockNumberOrTag, CallInput, CallRequest, Filter, Log};
+use std::sync::Arc;
+use crate::constants::CCIP_LOG_PAGE;
+use crate::functions::datafeeds::handle_multicall;
+use crate::functions::multicall3::Call3;
+use crate::provider::ChainProvider;
+use crate::types::{BlockRange, CcipMessageStatus, LaneToken, MessageState, RateLimiterState};
+use eyre::{Result, eyre};
+
+sol! {
+    struct EVMTokenAmount {
+        address token;
+        uint256 amount;
+    }
+
+    struct EVM2AnyMessage {
+        bytes receiver;
+        bytes data;
+        EVMTokenAmount[] tokenAmounts;
+        address feeToken;
+        bytes extraArgs;
+    }
+
+    interface IRouterClient {
+        function getFee(uint64 destinationChainSelector, EVM2AnyMessage message) external view returns (uint256 fee);
+        function ccipSend(uint64 destinationChainSelector, EVM2AnyMessage message) external payable returns (bytes32);
+    }
+
+    struct TokenBucket {
+        uint128 tokens;
+        uint32 lastUpdated;
+        bool isEnabled;
+        uint128 capacity;
+        uint128 rate;
+    }
+
+    /// Dynamic config of onRamps 1.2 and later
+    struct OnRampDynamicConfig {
+        address router;
+        uint16 maxNumberOfTokensPerMsg;
+        uint32 destGasOverhead;
+        uint16 destGasPerPayloadByte;
+        uint32 destDataAvailabilityOverheadGas;
+        uint16 destGasPerDataAvailabilityByte;
+        uint16 destDataAvailabilityMultiplierBps;
+        address priceRegistry;
+        uint32 maxDataBytes;
+        uint32 maxPerMsgGasLimit;
+    }
+
+    interface IEVM2EVMOnRamp {
+        function getDynamicConfig() external view returns (OnRampDynamicConfig memory);
+        function getSupportedTokens(uint64 destChainSelector) external view returns (address[] memory);
+        function getPoolBySourceToken(uint64 destChainSelector, address sourceToken) external view returns (address);
+        function currentRateLimiterState() external view returns (TokenBucket memory);
+    }
+
+    struct TimestampedPackedUint224 {
+        uint224 value;
+        uint32 timestamp;
+    }
+
+    interface IPriceRegistry {
+        function getFeeTokens() external view returns (address[] memory);
+        function getTokenPrice(address token) external view returns (TimestampedPackedUint224 memory);
+    }
+
+    interface ITokenPool {
+        function currentOnRampRateLimiterState(address onRamp) external view returns (TokenBucket memory);
+        function currentOffRampRateLimiterState(address offRamp) external view returns (TokenBucket memory);
+    }
+
+    /// Message as emitted by onRamps 1.2 and later
+    struct EVM2EVMMessage {
+        uint64 sourceChainSelector;
+        address sender;
+        address receiver;
+        uint64 sequenceNumber;
+        uint256 gasLimit;
+        bool strict;
+        uint64 nonce;
+        address feeToken;
+        uint256 feeTokenAmount;
+        bytes data;
+        EVMTokenAmount[] tokenAmounts;
+        bytes[] sourceTokenData;
+        bytes32 messageId;
+    }
+
+    event CCIPSendRequested(EVM2EVMMessage message);
+    event ExecutionStateChanged(uint64 indexed sequenceNumber, bytes32 indexed messageId, uint8 state, bytes returnData);
+
+    interface ICommitStore {
+        function getExpectedNextSequenceNumber() external view returns (uint64);
+    }
+
+    interface IEVM2EVMOffRamp {
+        function getExecutionState(uint64 sequenceNumber) external view returns (uint8);
+        function getPoolBySourceToken(address sourceToken) external view returns (address);
+        function currentRateLimiterState() external view returns (TokenBucket memory);
+    }
+
+    interface IERC20Metadata {
+        function symbol() external view returns (string memory);
+        function decimals() external view returns (uint8);
+        function allowance(address owner, address spender) external view returns (uint256);
+        function approve(address spender, uint256 amount) external returns (bool);
+    }
+}
+
+impl From<TokenBucket> for RateLimiterState {
+    fn from(bucket: TokenBucket) -> Self {
+        Self {
+            tokens: bucket.tokens,
+            last_updated: bucket.lastUpdated as u64,
+            is_enabled: bucket.isEnabled,
+            capacity: bucket.capacity,
+            rate: bucket.rate,
+        }
+    }
+}
+
+/// Calls `to` and decodes the return of the call
+async fn call<C: SolCall>(provider: Arc<ChainProvider>, to: Address, call: C, block: Option<BlockId>) -> Result<C::Return> {
+    let tx = CallRequest {
+        to: Some(to),
+        input: CallInput::new(call.abi_encode().into()),
+        ..Default::default()
+    };
+    match provider.call(tx, block).await {
+        Ok(r) => Ok(C::abi_decode_returns(&r, false)?),
+        Err(e) => Err(eyre!("Error calling {to}: {:?}", e)),
+    }
+}
+
+/// Tag of `EVMExtraArgsV1 { uint256 gasLimit }`, `bytes4(keccak256("CCIP EVMExtraArgsV1"))`
+pub const EVM_EXTRA_ARGS_V1_TAG: [u8; 4] = [0x97, 0xa6, 0x57, 0xc9];
+
+/// Extra args setting the gas limit of the execution on the destination, empty to use the default one
+pub fn encode_extra_args(gas_limit: Option<u64>) -> Bytes {
+    match gas_limit {
+        Some(gas_limit) => {
+            let mut args = EVM_EXTRA_ARGS_V1_TAG.to_vec();
+            args.extend_from_slice(&U256::from(gas_limit).to_be_bytes::<32>());
+            args.into()
+        },
+        None => Bytes::new(),
+    }
+}
+
+/// Message sent to an EVM `receiver`, paying fees in `fee_token` (`Address::ZERO` for native)
+pub fn build_message(
+    receiver: Address,
+    data: Bytes,
+    token_amounts: Vec<(Address, U256)>,
+    fee_token: Address,
+    gas_limit: Option<u64>,
+) -> EVM2AnyMessage {
+    EVM2AnyMessage {
+        // receivers are abi encoded, an address takes a full word
+        receiver: receiver.into_word().to_vec().into(),
+        data,
+        tokenAmounts: token_amounts
+            .into_iter()
+            .map(|(token, amount)| EVMTokenAmount { token, amount })
+            .collect(),
+        feeToken: fee_token,
+        extraArgs: encode_extra_args(gas_limit),
+    }
+}
+
+/// Fee charged by the router to send `message` to the chain of `selector`, in the fee token of the message
+pub async fn get_fee(
+    provider: Arc<ChainProvider>,
+    router: Address,
+    selector: u64,
+    message: EVM2AnyMessage,
+    block: Option<BlockId>,
+) -> Result<U256> {
+    let fee = call(provider, router, IRouterClient::getFeeCall { destinationChainSelector: selector, message }, block)
+        .await
+        .map_err(|e| eyre!("Error getting fee from router: {}", e))?;
+    Ok(fee.fee)
+}
+
+/// Tokens accepted to pay fees on the lane, read from the price registry of the onRamp
+pub async fn get_fee_tokens(provider: Arc<ChainProvider>, on_ramp: Address, block: Option<BlockId>) -> Result<Vec<Address>> {
+    let config = call(provider.clone(), on_ramp, IEVM2EVMOnRamp::getDynamicConfigCall {}, block).await?._0;
+    Ok(call(provider, config.priceRegistry, IPriceRegistry::getFeeTokensCall {}, block).await?._0)
+}
+
+/// Value of `amount` (smallest unit) of a source token in USD with 18 decimals, as the aggregate
+/// rate limiters count it. Priced by the price registry of the onRamp
+pub async fn get_usd_value(provider: Arc<ChainProvider>, on_ramp: Address, token: Address, amount: u128, block: Option<BlockId>) -> Result<u128> {
+    let config = call(provider.clone(), on_ramp, IEVM2EVMOnRamp::getDynamicConfigCall {}, block).await?._0;
+    let price = call(provider, config.priceRegistry, IPriceRegistry::getTokenPriceCall { token }, block).await?._0;
+    usd_value(U256::from_limbs(*price.value.as_limbs()), amount).ok_or(eyre!("USD value of {amount} of {token} overflows"))
+}
+
+/// USD value (18 decimals) of `amount` at a registry `price`: USD with 18 decimals per 1e18 of the smallest unit.
+/// `None` when it does not fit
+fn usd_value(price: U256, amount: u128) -> Option<u128> {
+    let value = price.checked_mul(U256::from(amount))? / U256::from(10u64).pow(U256::from(18));
+    u128::try_from(value).ok()
+}
+
+/// Aggregate rate limiter of the onRamp, the value (in USD with 18 decimals) that can leave the chain
+pub async fn get_on_ramp_rate_limiter(provider: Arc<ChainProvider>, on_ramp: Address, block: Option<BlockId>) -> Result<RateLimiterState> {
+    Ok(call(provider, on_ramp, IEVM2EVMOnRamp::currentRateLimiterStateCall {}, block).await?._0.into())
+}
+
+/// Aggregate rate limiter of the offRamp, the value (in USD with 18 decimals) that can be released on the destination
+pub async fn get_off_ramp_rate_limiter(provider: Arc<ChainProvider>, off_ramp: Address, block: Option<BlockId>) -> Result<RateLimiterState> {
+    Ok(call(provider, off_ramp, IEVM2EVMOffRamp::currentRateLimiterStateCall {}, block).await?._0.into())
+}
+
+/// Rate limiter of the destination pool of every source token for the offRamp, in the same order as the tokens.
+/// `None` for the tokens whose pool or bucket cannot be read
+pub async fn get_off_ramp_token_limiters(
+    provider: Arc<ChainProvider>,
+    off_ramp: Address,
+    source_tokens: &[Address],
+    block: Option<BlockId>,
+) -> Result<Vec<Option<RateLimiterState>>> {
+    if source_tokens.is_empty() {
+        return Ok(Vec::new())
+    }
+    let calls: Vec<Call3> = source_tokens
+        .iter()
+        .map(|token| Call3 {
+            target: off_ramp,
+            allowFailure: true,
+            callData: IEVM2EVMOffRamp::getPoolBySourceTokenCall { sourceToken: *token }.abi_encode().into(),
+        })
+        .collect();
+    let pools: Vec<Option<Address>> = handle_multicall(provider.clone(), calls, block)
+        .await?
+        .into_iter()
+        .map(|r| r.success.then(|| Address::abi_decode(&r.returnData, false).ok()).flatten())
+        .collect();
+    // pools that fail still get a call so the results stay aligned with the tokens
+    let calls: Vec<Call3> = pools
+        .iter()
+        .map(|pool| Call3 {
+            target: pool.unwrap_or(off_ramp),
+            allowFailure: true,
+            callData: ITokenPool::currentOffRampRateLimiterStateCall { offRamp: off_ramp }.abi_encode().into(),
+        })
+        .collect();
+    let results = handle_multicall(provider, calls, block).await?;
+    Ok(pools
+        .iter()
+        .zip(results)
+        .map(|(pool, r)| match (pool, r.success) {
+            (Some(_), true) => TokenBucket::abi_decode(&r.returnData, false).ok().map(Into::into),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Tokens that can be transferred to the chain of `selector`, with their pool and its rate limiter for the onRamp
+pub async fn get_lane_tokens(provider: Arc<ChainProvider>, on_ramp: Address, selector: u64, block: Option<BlockId>) -> Result<Vec<LaneToken>> {
+    let tokens = call(provider.clone(), on_ramp, IEVM2EVMOnRamp::getSupportedTokensCall { destChainSelector: selector }, block).await?._0;
+    let calls: Vec<Call3> = tokens
+        .iter()
+        .flat_map(|token| [
+            Call3 {
+                target: on_ramp,
+                allowFailure: true,
+                callData: IEVM2EVMOnRamp::getPoolBySourceTokenCall { destChainSelector: selector, sourceToken: *token }.abi_encode().into(),
+            },
+            Call3 { target: *token, allowFailure: true, callData: IERC20Metadata::symbolCall {}.abi_encode().into() },
+            Call3 { target: *token, allowFailure: true, callData: IERC20Metadata::decimalsCall {}.abi_encode().into() },
+        ])
+        .collect();
+    let results = handle_multicall(provider.clone(), calls, block).await?;
+    let mut lane_tokens: Vec<LaneToken> = tokens
+        .iter()
+        .zip(results.chunks(3))
+        .map(|(token, r)| {
+            let data = |i: usize| r.get(i).filter(|r| r.success).map(|r| &r.returnData);
+            LaneToken {
+                token: *token,
+                symbol: data(1).and_then(|d| String::abi_decode(d, false).ok()),
+                decimals: data(2).and_then(|d| u8::abi_decode(d, false).ok()),
+                pool: data(0).and_then(|d| Address::abi_decode(d, false).ok()),
+                rate_limiter: None,
+            }
+        })
+        .collect();
+    let pools: Vec<(usize, Address)> = lane_tokens
+        .iter()
+        .enumerate()
+        .filter_map(|(i, t)| t.pool.map(|p| (i, p)))
+        .collect();
+    let calls: Vec<Call3> = pools
+        .iter()
+        .map(|(_, pool)| Call3 {
+            target: *pool,
+            allowFailure: true,
+            callData: ITokenPool::currentOnRampRateLimiterStateCall { onRamp: on_ramp }.abi_encode().into(),
+        })
+        .collect();
+    if !calls.is_empty() {
+        let results = handle_multicall(provider, calls, block).await?;
+        for ((i, _), r) in pools.into_iter().zip(results) {
+            if r.success {
+                lane_tokens[i].rate_limiter = TokenBucket::abi_decode(&r.returnData, false).ok().map(Into::into);
+            }
+        }
+    }
+    Ok(lane_tokens)
+}
+
+/// Message sent through an onRamp, with the transaction that sent it
+#[derive(Debug, Clone)]
+pub struct SentMessage {
+    pub message_id: B256,
+    pub sequence_number: u64,
+    pub source_tx: Option<B256>,
+    pub source_block: Option<u64>,
+}
+
+fn decode_sent_message(on_ramp: Address, log: &Log) -> Option<SentMessage> {
+    if log.address != on_ramp || log.topics.first() != Some(&CCIPSendRequested::SIGNATURE_HASH) {
+        return None
+    }
+    let event = CCIPSendRequested::decode_raw_log(log.topics.iter().copied(), &log.data, true).ok()?;
+    Some(SentMessage {
+        message_id: event.message.messageId,
+        sequence_number: event.message.sequenceNumber,
+        source_tx: log.transaction_hash,
+        source_block: log.block_number.map(|b| b.to::<u64>()),
+    })
+}
+
+/// Messages sent through `on_ramp` by the transaction `tx_hash`
+pub async fn get_sent_messages(provider: Arc<ChainProvider>, on_ramp: Address, tx_hash: B256) -> Result<Vec<SentMessage>> {
+    let receipt = provider
+        .get_transaction_receipt(tx_hash)
+        .await?
+        .ok_or(eyre!("Transaction {tx_hash} not found"))?;
+    Ok(receipt.logs.iter().filter_map(|log| decode_sent_message(on_ramp, log)).collect())
+}
+
+/// Timestamp of block `number`
+pub async fn block_timestamp(provider: Arc<ChainProvider>, number: u64) -> Result<u64> {
+    let block = provider
+        .get_block_by_number(BlockNumberOrTag::Number(U64::from(number)), false)
+        .await?
+        .ok_or(eyre!("Block {number} not found"))?;
+    Ok(block.header.timestamp.to::<u64>())
+}
+
+/// First block mined at or after `timestamp`, bisecting on the block timestamps
+/// (the latest block when every block is older)
+pub async fn block_at_timestamp(provider: Arc<ChainProvider>, timestamp: u64) -> Result<u64> {
+    let (mut low, mut high) = (0, provider.get_block_number().await?.to::<u64>());
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if block_timestamp(provider.clone(), mid).await? < timestamp {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    Ok(low)
+}
+
+/// Reads the logs of `filter` between `from_block` and `to_block` in pages of `CCIP_LOG_PAGE` blocks,
+/// the newest page first when `backwards`, until `pick` finds something in a page
+async fn search_logs<T>(
+    provider: Arc<ChainProvider>,
+    filter: Filter,
+    from_block: u64,
+    to_block: u64,
+    backwards: bool,
+    pick: impl Fn(&[Log]) -> Option<T>,
+) -> Result<Option<T>> {
+    if from_block > to_block {
+        return Ok(None)
+    }
+    let pages = (to_block - from_block) / CCIP_LOG_PAGE + 1;
+    for i in 0..pages {
+        let page = if backwards { pages - 1 - i } else { i };
+        let start = from_block + page * CCIP_LOG_PAGE;
+        let end = (start + CCIP_LOG_PAGE - 1).min(to_block);
+        let logs = provider.get_logs(filter.clone().from_block(start).to_block(end)).await?;
+        if let Some(found) = pick(&logs) {
+            return Ok(Some(found))
+        }
+    }
+    Ok(None)
+}
+
+/// Message `message_id` sent through `on_ramp` between `from_block` and the latest block, newest blocks first.
+/// Message ids are not indexed, every `CCIPSendRequested` log of the range is decoded
+pub async fn find_sent_message(provider: Arc<ChainProvider>, on_ramp: Address, message_id: B256, from_block: u64) -> Result<Option<SentMessage>> {
+    let filter = Filter::new()
+        .address(on_ramp)
+        .event_signature(CCIPSendRequested::SIGNATURE_HASH);
+    let latest = provider.get_block_number().await?.to::<u64>();
+    search_logs(provider, filter, from_block, latest, true, |logs| {
+        logs.iter()
+            .filter_map(|log| decode_sent_message(on_ramp, log))
+            .find(|m| m.message_id == message_id)
+    }).await
+}
+
+/// State of a sent message on the destination: executed or failed from the offRamp,
+/// committed once the commit store expects a later sequence number, sent otherwise.
+/// The transaction that set the final state is looked for from `from_block` of the destination,
+/// the searched range is kept when it is not found
+pub async fn get_message_status(
+    provider: Arc<ChainProvider>,
+    commit_store: Address,
+    off_ramp: Address,
+    message: &SentMessage,
+    from_block: u64,
+) -> Result<CcipMessageStatus> {
+    let execution = call(provider.clone(), off_ramp, IEVM2EVMOffRamp::getExecutionStateCall { sequenceNumber: message.sequence_number }, None).await?._0;
+    let mut state = match execution {
+        1 => MessageState::InProgress,
+        2 => MessageState::Executed,
+        3 => MessageState::Failed,
+        _ => MessageState::Sent,
+    };
+    if state == MessageState::Sent {
+        let next = call(provider.clone(), commit_store, ICommitStore::getExpectedNextSequenceNumberCall {}, None).await?._0;
+        if next > message.sequence_number {
+            state = MessageState::Committed;
+        }
+    }
+    let mut destination_tx = None;
+    let mut searched_blocks = None;
+    if state.is_final() {
+        let filter = Filter::new()
+            .address(off_ramp)
+            .event_signature(ExecutionStateChanged::SIGNATURE_HASH)
+            .topic1(B256::from(U256::from(message.sequence_number)))
+            .topic2(message.message_id);
+        let latest = provider.get_block_number().await?.to::<u64>();
+        // a failed message can be executed again, the change to the current state is the one wanted
+        destination_tx = search_logs(provider, filter, from_block, latest, false, |logs| {
+            logs.iter()
+                .filter(|log| ExecutionStateChanged::decode_raw_log(log.topics.iter().copied(), &log.data, true)
+                    .map_or(false, |e| e.state == execution))
+                .find_map(|log| log.transaction_hash)
+        }).await?;
+        if destination_tx.is_none() {
+            searched_blocks = Some(BlockRange { from: from_block, to: latest });
+        }
+    }
+    Ok(CcipMessageStatus {
+        message_id: message.message_id,
+        sequence_number: message.sequence_number,
+        source_tx: message.source_tx,
+        state,
+        destination_tx,
+        searched_blocks,
+    })
+}
+
+/// Calldata of `ccipSend` on the router
+pub fn ccip_send_calldata(selector: u64, message: EVM2AnyMessage) -> Bytes {
+    IRouterClient::ccipSendCall { destinationChainSelector: selector, message }.abi_encode().into()
+}
+
+/// Calldata approving `spender` to take `amount` of a token
+pub fn approve_calldata(spender: Address, amount: U256) -> Bytes {
+    IERC20Metadata::approveCall { spender, amount }.abi_encode().into()
+}
+
+/// Amount of `token` that `owner` lets `spender` take
+pub async fn get_allowance(provider: Arc<ChainProvider>, token: Address, owner: Address, spender: Address) -> Result<U256> {
+    Ok(call(provider, token, IERC20Metadata::allowanceCall { owner, spender }, None).await?._0)
+}
+
+/// Simulates `ccipSend` from `from` with `eth_call`, returning the message id the router would give
+pub async fn simulate_ccip_send(provider: Arc<ChainProvider>, from: Address, router: Address, value: U256, calldata: Bytes) -> Result<B256> {
+    let tx = CallRequest {
+        from: Some(from),
+        to: Some(router),
+        value: Some(value),
+        input: CallInput::new(calldata),
+        ..Default::default()
+    };
+    match provider.call(tx, None).await {
+        Ok(r) => Ok(IRouterClient::ccipSendCall::abi_decode_returns(&r, false)?._0),
+        Err(e) => Err(eyre!("Simulation of ccipSend failed: {:?}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const E18: u128 = 1_000_000_000_000_000_000;
+
+    #[test]
+    fn usd_value_scales_by_1e18() {
+        // 18 decimals token at $2000
+        assert_eq!(usd_value(U256::from(2000 * E18), E18), Some(2000 * E18));
+        assert_eq!(usd_value(U256::from(2000 * E18), E18 / 4), Some(500 * E18));
+        assert_eq!(usd_value(U256::from(2000 * E18), 1), Some(2000));
+        // 6 decimals token at $1: the price is per 1e18 units, 1e30
+        assert_eq!(usd_value(U256::from(E18) * U256::from(1_000_000_000_000u64), 5_000_000), Some(5 * E18));
+        assert_eq!(usd_value(U256::ZERO, E18), Some(0));
+        // values under a unit of 1e-18 USD round down
+        assert_eq!(usd_value(U256::from(1), E18 - 1), Some(0));
+    }
+
+    #[test]
+    fn usd_value_overflow() {
+        // does not fit in u128
+        assert_eq!(usd_value(U256::from(u128::MAX) * U256::from(E18), 2), None);
+        // price times amount does not fit in U256
+        assert_eq!(usd_value(U256::MAX, 2), None);
+        assert_eq!(usd_value(U256::from(u128::MAX) * U256::from(E18), 1), Some(u128::MAX));
+    }
+}
@@ -1,16 +1,20 @@
 use alloy_sol_types::{sol, SolCall, SolType, SolValue};
-use alloy_primitives::{utils::format_units, U256, Address};
-use alloy_providers::provider::{Provider, TempProvider};
-use alloy_rpc_types::{CallRequest, CallInput};
+use alloy_primitives::{I256, U256, Address, Bytes};
+use alloy_providers::provider::TempProvider;
+use alloy_rpc_types::{BlockId, CallRequest, CallInput};
 use alloy_chains::Chain;
-use alloy_transport_http::Http;
-use std::sync::Arc;
-use reqwest::Client;
+use std::{future::Future, sync::Arc};
 use datafeeds::{Oracle, OraclesIndex};
 use crate::constants;
 use crate::functions::multicall3;
 use crate::functions::multicall3::Call3;
+use crate::provider::ChainProvider;
+use crate::types::{as_string, as_opt_string, AnswerStatus, Description, PairAnswer, Price, RoundData};
 use eyre::{Result, eyre};
+use serde::Serialize;
+
+/// Rounds sampled on each step of the timestamp search
+pub const ROUND_SEARCH_BATCH: usize = 32;
 
 sol!(
     struct GetRoundDataReturn {
@@ -34,8 +38,34 @@ impl std::fmt::Debug for GetRoundDataReturn {
     }
 }
 
+/// Round id as exposed by the proxy: `(phaseId << 64) | aggregatorRoundId`
+pub fn phase_round_id(phase: u16, round: u128) -> u128 {
+    ((phase as u128) << 64) | round
+}
+
+/// Splits a proxy round id into its phase and the aggregator round id
+pub fn split_round_id(round_id: u128) -> (u16, u128) {
+    ((round_id >> 64) as u16, round_id & u64::MAX as u128)
+}
+
+/// An aggregator that served the proxy during a phase
+#[derive(Debug, Serialize)]
+pub struct Phase {
+    pub id: u16,
+    #[serde(serialize_with = "as_string")]
+    pub aggregator: Address,
+    #[serde(serialize_with = "as_string")]
+    pub version: U256,
+    /// first (phase-aware) round id served by the aggregator
+    #[serde(serialize_with = "as_string")]
+    pub first_round: u128,
+    /// last (phase-aware) round id served by the aggregator, if it could be read
+    #[serde(serialize_with = "as_opt_string")]
+    pub last_round: Option<u128>,
+}
+
 /// Helper for multicalls
-pub async fn handle_multicall(provider: Arc<Provider<Http<Client>>>, calls: Vec<Call3>) -> Result<Vec<multicall3::Result>> {
+pub async fn handle_multicall(provider: Arc<ChainProvider>, calls: Vec<Call3>, block: Option<BlockId>) -> Result<Vec<multicall3::Result>> {
     let mc = multicall3::aggregate3Call { calls };
     let ci = CallInput::new(mc.abi_encode().into());
     let cr = CallRequest {
@@ -43,22 +73,24 @@ pub async fn handle_multicall(provider: Arc<Provider<Http<Client>>>, calls: Vec<
         input: ci,
         ..Default::default()
     };
-    let r = provider.call(cr, None).await?;
-    Ok(multicall3::MultiResult::abi_decode(&r, false).expect("Error on returned data"))           
+    let r = provider.call(cr, block).await?;
+    Ok(multicall3::MultiResult::abi_decode(&r, false)?)           
 }
 
+/// Latest answer of the proxy, answers are signed (`int256`)
 pub async fn get_latest_answer(
-    provider: Arc<Provider<Http<Client>>>,
+    provider: Arc<ChainProvider>,
     oracle: Address,
-) -> Result<U256> {
+    block: Option<BlockId>,
+) -> Result<I256> {
     let tx = CallRequest {
         to: Some(oracle),
         input: CallInput::new(datafeeds::contracts::EACAggregatorProxy::EACAggregatorProxy::latestAnswerCall{}.abi_encode().into()),
         ..Default::default()
     };
-    match provider.call(tx, None).await {
+    match provider.call(tx, block).await {
         Ok(r) => {
-            let b: U256 = U256::abi_decode(&r, false).expect("cannot deserialize result");
+            let b: I256 = I256::abi_decode(&r, false)?;
             Ok(b)
         },
         Err(e) => Err(eyre!("Could not get latest answer {:?}", e))
@@ -66,9 +98,10 @@ pub async fn get_latest_answer(
 }
 
 pub async fn get_round_data(
-    provider: Arc<Provider<Http<Client>>>, 
+    provider: Arc<ChainProvider>, 
     oracle: Address, 
     round_id: u128,
+    block: Option<BlockId>,
 ) -> Result<GetRoundDataReturn> {
     let tx = CallRequest {
         to: Some(oracle),
@@ -81,7 +114,7 @@ pub async fn get_round_data(
                 ),
         ..Default::default()
     };
-    match provider.call(tx, None).await {
+    match provider.call(tx, block).await {
         Ok(r) => {                      
             Ok(<GetRoundDataReturn as SolValue>::abi_decode(&r, false)?)
         },
@@ -89,40 +122,38 @@ pub async fn get_round_data(
     }        
 }
 
-pub async fn get_latest_round_data(provider: Arc<Provider<Http<Client>>>, addr: Address) -> Result<GetRoundDataReturn> {
+pub async fn get_latest_round_data(provider: Arc<ChainProvider>, addr: Address, block: Option<BlockId>) -> Result<GetRoundDataReturn> {
     let tx = CallRequest {
         to: Some(addr),
         input: CallInput::new(datafeeds::contracts::EACAggregatorProxy::EACAggregatorProxy::latestRoundDataCall{}.abi_encode().into()),
         ..Default::default()
     };
-    match provider.call(tx, None).await {
+    match provider.call(tx, block).await {
         Ok(r) => {
-            let resp = <GetRoundDataReturn as SolValue>::abi_decode(&r, false).unwrap();
+            let resp = <GetRoundDataReturn as SolValue>::abi_decode(&r, false)?;
             Ok(resp)
         },
         Err(e) => Err(eyre!("Error fetching latest round data for {addr}: {:?}", e)),
     }    
 }
 
-pub async fn get_description(provider: Arc<Provider<Http<Client>>>, oracle: &Oracle) {
+pub async fn get_description(provider: Arc<ChainProvider>, oracle: &Oracle, block: Option<BlockId>) -> Result<Description> {
+    let proxy_address = oracle.proxy_address.ok_or(eyre!("Oracle has no proxy address"))?;
     let tx = CallRequest {
-        to: Some(oracle.proxy_address.unwrap()),
+        to: Some(proxy_address),
         input: CallInput::new(datafeeds::contracts::EACAggregatorProxy::EACAggregatorProxy::descriptionCall{}.abi_encode().into()),
         ..Default::default()
     };
-    match provider.call(tx, None).await {
+    match provider.call(tx, block).await {
         Ok(r) => {
-            let response: String = String::abi_decode(&r, false).expect("cannot deserialize result");
-            println!("Description for {} is: {}", 
-            oracle.proxy_address.unwrap(), 
-            response 
-        )
+            let description: String = String::abi_decode(&r, false)?;
+            Ok(Description { proxy_address, description })
         },
-        Err(e) => println!("Error: {:#?}", e)
+        Err(e) => Err(eyre!("Error fetching description for {proxy_address}: {:?}", e))
     }        
 }
 
-pub async fn get_aggregators_version(provider: Arc<Provider<Http<Client>>>, addresses: Vec<Address>) -> Result<Vec<U256>> {
+pub async fn get_aggregators_version(provider: Arc<ChainProvider>, addresses: Vec<Address>, block: Option<BlockId>) -> Result<Vec<U256>> {
     let all_timestamps: Vec<Call3> = addresses
         .clone()
         .into_iter()
@@ -134,154 +165,447 @@ pub async fn get_aggregators_version(provider: Arc<Provider<Http<Client>>>, addr
             }
         )
         .collect();
-    let mut response = Vec::new();
-    match handle_multicall(provider, all_timestamps).await {
-        Ok(r) => {
-            for (result, aggr) in r.into_iter().zip(addresses) {
-                if result.success {
-                    let res_p = U256::try_from_be_slice(&result.returnData).unwrap_or(U256::ZERO);
-                    //println!("Aggregator {} version is {}", aggr, res_p);
-                    response.push(res_p);
-                } else {
-                    println!("Cannot retrieve version for {}", aggr);
-                }
+    // versions that cannot be read are left as zero
+    let response = handle_multicall(provider, all_timestamps, block)
+        .await?
+        .into_iter()
+        .map(|result|
+            if result.success {
+                U256::try_from_be_slice(&result.returnData).unwrap_or(U256::ZERO)
+            } else {
+                U256::ZERO
             }
-        },
-        Err(e) => panic!("{}", e)
-    };
+        )
+        .collect();
     Ok(response) 
     //every version needs to be handled differently (see  https://github.com/pappas999/historical-price-feed-data/blob/main/src/historical-price-ea/index.js 115-134)   
 }
 
-// pub fn find_round_id_timestamp() -> u128 {
-//  needs to limit lower point (with a chain related hardcoded values)
-//  what the ref does is an iteration of chunked round_ids for the logs of "newRound" in the Aggregator contract
-//  gets a list of valid round ids where later does a binary search to find lower & upper bands
-//}
+/// Finds the round that was current at `timestamp`: the latest round whose `updatedAt` is not after it.
+/// Narrows down the phase first and then searches its rounds, sampling `ROUND_SEARCH_BATCH` rounds per multicall.
+/// Returns `None` if the feed had no answer yet at that time
+pub async fn find_round_id_timestamp(
+    provider: Arc<ChainProvider>,
+    oracle: &Oracle,
+    timestamp: u64,
+    block: Option<BlockId>,
+) -> Result<Option<GetRoundDataReturn>> {
+    let proxy = oracle.proxy_address.ok_or(eyre!("Oracle has no proxy address"))?;
+    let phases = get_phases(provider.clone(), oracle, block).await?;
+    find_round_in_phases(provider, proxy, &phases, timestamp, block).await
+}
+
+async fn find_round_in_phases(
+    provider: Arc<ChainProvider>,
+    proxy: Address,
+    phases: &[Phase],
+    timestamp: u64,
+    block: Option<BlockId>,
+) -> Result<Option<GetRoundDataReturn>> {
+    search_phases(phases, timestamp, |ids| get_rounds(provider.clone(), proxy, ids, block)).await
+}
 
+/// Search of `find_round_in_phases`, with `read_rounds` reading a batch of (phase-aware) round ids
+/// like `get_rounds` does
+async fn search_phases<F, Fut>(
+    phases: &[Phase],
+    timestamp: u64,
+    read_rounds: F,
+) -> Result<Option<GetRoundDataReturn>>
+where
+    F: Fn(Vec<u128>) -> Fut,
+    Fut: Future<Output = Result<Vec<Option<GetRoundDataReturn>>>>,
+{
+    let target = U256::from(timestamp);
+    let is_before = |r: &GetRoundDataReturn| r.updatedAt != U256::ZERO && r.updatedAt <= target;
 
+    let phases: Vec<&Phase> = phases
+        .iter()
+        .filter(|p| p.last_round.is_some())
+        .collect();
+    // the phase is the last one whose first round was already reported at `timestamp`
+    let first_rounds = read_rounds(phases.iter().map(|p| p.first_round).collect()).await?;
+    let mut found = None;
+    for (phase, first) in phases.into_iter().zip(first_rounds) {
+        match first {
+            Some(first) if is_before(&first) => found = Some((phase, first)),
+            Some(_) => break,
+            None => continue,
+        }
+    }
+    let Some((phase, first)) = found else {
+        return Ok(None)
+    };
 
-pub async fn get_multiple_round_data(
-    provider: Arc<Provider<Http<Client>>>, 
-    oracle: Address,
-    round_ids: Vec<u128>
+    let (_, mut lo) = split_round_id(phase.first_round);
+    let (_, last) = split_round_id(phase.last_round.unwrap());
+    let mut best = first;
+    // last round may be after the timestamp, hi is exclusive
+    let mut hi = last + 1;
+    while hi - lo > 1 {
+        let span = hi - lo - 1;
+        let step = (span / ROUND_SEARCH_BATCH as u128).max(1);
+        let samples: Vec<u128> = (1..=ROUND_SEARCH_BATCH as u128)
+            .map(|i| lo + i * step)
+            .take_while(|r| *r < hi)
+            .collect();
+        let rounds = read_rounds(samples.iter().map(|r| phase_round_id(phase.id, *r)).collect()).await?;
+        let (prev_lo, prev_hi) = (lo, hi);
+        for (sample, round) in samples.into_iter().zip(rounds) {
+            match round {
+                Some(round) if is_before(&round) => {
+                    lo = sample;
+                    best = round;
+                },
+                Some(_) => {
+                    hi = sample;
+                    break;
+                },
+                None => continue,
+            }
+        }
+        if (lo, hi) == (prev_lo, prev_hi) {
+            // all sampled rounds failed, cannot narrow further
+            break;
+        }
+    }
+    Ok(Some(best))
+}
+
+/// Collects every round updated between `from` and `to` (inclusive), walking all the phases of the proxy.
+/// Rounds are read in multicalls of `chunk_size` round ids
+pub async fn get_history(
+    provider: Arc<ChainProvider>,
+    oracle: &Oracle,
+    from: u64,
+    to: u64,
+    chunk_size: usize,
+    block: Option<BlockId>,
 ) -> Result<Vec<GetRoundDataReturn>> {
-    let mut all_queries: Vec<Call3> = Vec::new();
-    for rid in round_ids.clone().into_iter() {
-        all_queries.push(
+    if from > to {
+        return Err(eyre!("Start of range ({from}) is after its end ({to})"))
+    }
+    let proxy = oracle.proxy_address.ok_or(eyre!("Oracle has no proxy address"))?;
+    let phases = get_phases(provider.clone(), oracle, block).await?;
+    // start from the round that was current at `from`, or from the very first round
+    let start = match find_round_in_phases(provider.clone(), proxy, &phases, from, block).await? {
+        Some(r) => r.roundId,
+        None => phases.first().ok_or(eyre!("Proxy has no phases"))?.first_round,
+    };
+    let (start_phase, start_round) = split_round_id(start);
+    let (from, to) = (U256::from(from), U256::from(to));
+    let mut history = Vec::new();
+    for phase in phases.iter().filter(|p| p.id >= start_phase) {
+        let Some(last_round) = phase.last_round else {
+            continue
+        };
+        let (_, last) = split_round_id(last_round);
+        let first = if phase.id == start_phase { start_round } else { 1 };
+        let mut round = first;
+        while round <= last {
+            let chunk_end = (round + chunk_size.max(1) as u128 - 1).min(last);
+            let ids = (round..=chunk_end).map(|r| phase_round_id(phase.id, r)).collect();
+            for r in get_rounds(provider.clone(), proxy, ids, block).await?.into_iter().flatten() {
+                if r.updatedAt > to {
+                    return Ok(history)
+                }
+                if r.updatedAt >= from {
+                    history.push(r);
+                }
+            }
+            round = chunk_end + 1;
+        }
+    }
+    Ok(history)
+}
+
+/// Gets round data for every round id, keeping `None` for rounds that could not be read
+pub async fn get_rounds(
+    provider: Arc<ChainProvider>,
+    oracle: Address,
+    round_ids: Vec<u128>,
+    block: Option<BlockId>,
+) -> Result<Vec<Option<GetRoundDataReturn>>> {
+    if round_ids.is_empty() {
+        return Ok(Vec::new())
+    }
+    let all_queries: Vec<Call3> = round_ids
+        .into_iter()
+        .map(|rid|
             Call3 {
                 target: oracle,
                 allowFailure: true,
-                callData: datafeeds::contracts::EACAggregatorProxy::EACAggregatorProxy::getRoundDataCall{_roundId: rid.clone()}.abi_encode().into()
-            } 
+                callData: datafeeds::contracts::EACAggregatorProxy::EACAggregatorProxy::getRoundDataCall{_roundId: rid}.abi_encode().into()
+            }
         )
-    }
-    let mut all_responses: Vec<GetRoundDataReturn> = Vec::new();
-    
-    let all_results = handle_multicall(provider, all_queries).await?;
-    for result in all_results.into_iter() {
-        if result.success {
-            let response = <GetRoundDataReturn as SolValue>::abi_decode(&result.returnData, false).unwrap();
-            all_responses.push(response)          
-        } else {
-            //all_responses.push(None);
-            println!("Error getting round data for {}", oracle)
-        }
-    }
+        .collect();
+    let all_results = handle_multicall(provider, all_queries, block).await?;
+    Ok(all_results
+        .into_iter()
+        .map(|result|
+            if result.success {
+                <GetRoundDataReturn as SolValue>::abi_decode(&result.returnData, false).ok()
+            } else {
+                None
+            }
+        )
+        .collect())
+}
 
+pub async fn get_multiple_round_data(
+    provider: Arc<ChainProvider>, 
+    oracle: Address,
+    round_ids: Vec<u128>,
+    block: Option<BlockId>,
+) -> Result<Vec<GetRoundDataReturn>> {
+    // rounds that cannot be read are skipped
+    let all_responses: Vec<GetRoundDataReturn> = get_rounds(provider, oracle, round_ids, block)
+        .await?
+        .into_iter()
+        .flatten()
+        .collect();
     Ok(all_responses)                   
 }
 
-pub async fn get_multiple_latest_answer(
-    provider: Arc<Provider<Http<Client>>>, 
-    chain: Chain, 
-    token: Vec<String>, 
-    base: Vec<String>
-) -> Result<Vec<U256>> {
-    let mut tokens: Vec<String> = Vec::new();
-    let mut bases: Vec<String> =  Vec::new();
-    let datafeeds = OraclesIndex::load_reference_feeds(chain).await;
+/// Calls `call_data` on the proxy of every token/base pair of `datafeeds` in a single multicall.
+/// Returns every pair (`TOKEN/BASE`) in order, with its oracle and the call result when it has a proxy
+async fn multicall_pairs<'a>(
+    provider: Arc<ChainProvider>,
+    datafeeds: &'a OraclesIndex,
+    token: Vec<String>,
+    base: Vec<String>,
+    call_data: Bytes,
+    block: Option<BlockId>,
+) -> Result<Vec<(String, Option<(&'a Oracle, multicall3::Result)>)>> {
+    let mut pairs: Vec<(String, Option<&Oracle>)> = Vec::new();
     let mut all_queries: Vec<Call3> = Vec::new();
-    let mut all_oracles: Vec<&Oracle> = Vec::new();
     for (t,b) in token.into_iter().zip(base) {
         let tt = t.to_uppercase();
         let bb = b.to_uppercase();
-        if let Some(oracle) = datafeeds.get_oracle(&tt, &bb) {
-            all_oracles.push(oracle);
-            tokens.push(tt);
-            bases.push(bb);
+        let oracle = datafeeds.get_oracle(&tt, &bb).and_then(|o| o.proxy_address.map(|p| (o, p)));
+        if let Some((_, proxy)) = oracle {
             all_queries.push(
                 Call3 {
-                    target: oracle.proxy_address.unwrap(),
+                    target: proxy,
                     allowFailure: true,
-                    callData: datafeeds::contracts::EACAggregatorProxy::EACAggregatorProxy::latestAnswerCall{}.abi_encode().into()
-                } 
-            )
+                    callData: call_data.clone()
+                }
+            );
         }
+        pairs.push((format!("{}/{}", tt, bb), oracle.map(|(o, _)| o)));
     }
-    let mut vr = Vec::<U256>::new();
-    match handle_multicall(provider, all_queries).await {
-        Ok(res) => {
-            for (((t,b) , o), r) in tokens.into_iter().zip(bases).zip(all_oracles).zip(res) {
-                if r.success {
-                    let val = U256::try_from_be_slice(&r.returnData).unwrap_or(U256::ZERO);
-                    vr.push(val);
-                    println!("Latest answer for {}: {} {}", t, format_units(val, o.decimals.unwrap()).unwrap(), b)
-                } else {
-                    println!("Error getting latest answer for {}/{}", t, b)
-                }
+    let mut res = match all_queries.is_empty() {
+        true => Vec::new(),
+        false => handle_multicall(provider, all_queries, block).await?,
+    }.into_iter();
+    Ok(pairs
+        .into_iter()
+        .map(|(pair, oracle)| (pair, oracle.and_then(|o| res.next().map(|r| (o, r)))))
+        .collect())
+}
+
+/// Latest answer of every token/base pair in a single multicall.
+/// Results are in the same order as the pairs, with the pairs without oracle or answer reported as such
+pub async fn get_multiple_latest_answer(
+    provider: Arc<ChainProvider>, 
+    datafeeds: &OraclesIndex,
+    chain: Chain, 
+    token: Vec<String>, 
+    base: Vec<String>,
+    block: Option<BlockId>,
+) -> Result<Vec<PairAnswer>> {
+    let call_data = datafeeds::contracts::EACAggregatorProxy::EACAggregatorProxy::latestAnswerCall{}.abi_encode().into();
+    let results = multicall_pairs(provider, datafeeds, token, base, call_data, block).await?;
+    Ok(results
+        .into_iter()
+        .map(|(pair, result)| {
+            let Some((oracle, r)) = result else {
+                return PairAnswer { chain: chain.to_string(), pair, status: AnswerStatus::NoFeed, price: None, route: None, oldest_updated_at: None }
+            };
+            let raw = match r.success {
+                true => I256::abi_decode(&r.returnData, false).ok(),
+                false => None,
+            };
+            match raw {
+                Some(raw) => PairAnswer {
+                    chain: chain.to_string(),
+                    price: Some(Price {
+                        chain: chain.to_string(),
+                        pair: pair.clone(),
+                        raw,
+                        decimals: oracle.decimals.unwrap_or_default(),
+                    }),
+                    pair,
+                    status: AnswerStatus::Ok,
+                    route: None,
+                    oldest_updated_at: None,
+                },
+                None => PairAnswer { chain: chain.to_string(), pair, status: AnswerStatus::CallFailed, price: None, route: None, oldest_updated_at: None },
             }
-        },
-        Err(e) => panic!("{}", e)
+        })
+        .collect())
+}
+
+/// Latest round data of every token/base pair found in `datafeeds`, in a single multicall.
+/// Pairs without oracle or answer are skipped
+pub async fn get_multiple_latest_round_data(
+    provider: Arc<ChainProvider>, 
+    datafeeds: &OraclesIndex,
+    token: Vec<String>, 
+    base: Vec<String>,
+    block: Option<BlockId>,
+) -> Result<Vec<RoundData>> {
+    let call_data = datafeeds::contracts::EACAggregatorProxy::EACAggregatorProxy::latestRoundDataCall{}.abi_encode().into();
+    let results = multicall_pairs(provider, datafeeds, token, base, call_data, block).await?;
+    Ok(results
+        .into_iter()
+        .filter_map(|(pair, result)| {
+            let (oracle, r) = result?;
+            if !r.success {
+                return None
+            }
+            let round = <GetRoundDataReturn as SolValue>::abi_decode(&r.returnData, false).ok()?;
+            Some(RoundData::new(&pair, &round, oracle.decimals.unwrap_or_default()))
+        })
+        .collect())
+}
+
+/// First block in `[from_block, to_block]` at which the proxy's latest round was `round_id` or a later one,
+/// found by bisecting on `latestRoundData` (needs an archive node).
+/// Returns the block with the latest round at it, `None` if the round was not reported by `to_block`
+pub async fn find_block_of_round(
+    provider: Arc<ChainProvider>,
+    proxy: Address,
+    round_id: u128,
+    from_block: u64,
+    to_block: u64,
+) -> Result<Option<(u64, GetRoundDataReturn)>> {
+    // reads that fail (e.g. before the proxy was deployed) count as not reported yet
+    let reported_at = |block: u64| {
+        let provider = provider.clone();
+        async move {
+            match get_latest_round_data(provider, proxy, Some(block.into())).await {
+                Ok(r) if r.roundId >= round_id => Some(r),
+                _ => None,
+            }
+        }
+    };
+    let Some(mut found) = reported_at(to_block).await else {
+        return Ok(None)
+    };
+    let (mut lo, mut hi) = (from_block, to_block);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        match reported_at(mid).await {
+            Some(r) => {
+                hi = mid;
+                found = r;
+            },
+            None => lo = mid + 1,
+        }
     }
+    Ok(Some((hi, found)))
+}
 
-    Ok(vr)
+/// Aggregator currently used by the proxy
+pub async fn get_current_aggregator(provider: Arc<ChainProvider>, proxy: Address, block: Option<BlockId>) -> Result<Address> {
+    let tx = CallRequest {
+        to: Some(proxy),
+        input: CallInput::new(datafeeds::contracts::EACAggregatorProxy::EACAggregatorProxy::aggregatorCall{}.abi_encode().into()),
+        ..Default::default()
+    };
+    match provider.call(tx, block).await {
+        Ok(r) => Ok(Address::abi_decode(&r, false)?),
+        Err(e) => Err(eyre!("Error fetching aggregator of {proxy}: {:?}", e))
+    }
 }
 
 // WIP to collect historical data
 
 /// Gets all the Aggregators used by this proxy, in order to get historical data
-pub async fn get_aggregators(provider: Arc<Provider<Http<Client>>>, oracle: &Oracle) -> Vec<Address> {
+pub async fn get_aggregators(provider: Arc<ChainProvider>, oracle: &Oracle, block: Option<BlockId>) -> Result<Vec<Address>> {
+    let proxy = oracle.proxy_address.ok_or(eyre!("Oracle has no proxy address"))?;
     // get current phase
     let curr_phase_req = CallRequest {
-        to: Some(oracle.proxy_address.unwrap()),
+        to: Some(proxy),
         input: CallInput::new(datafeeds::contracts::EACAggregatorProxy::EACAggregatorProxy::phaseIdCall{}.abi_encode().into()),
         ..Default::default()
     };    
-    match provider.call(curr_phase_req, None).await {
+    match provider.call(curr_phase_req, block).await {
         Ok(phase) => {
             // collects phaseAggregators() from 1 to current (Multicall)
-            let phase: u16 = u16::abi_decode(&phase, false).unwrap();
+            let phase: u16 = u16::abi_decode(&phase, false)?;
             let mut all_phases_calls: Vec<Call3> = Vec::new();
-            for i in 1..phase {
+            for i in 1..=phase {
                 all_phases_calls.push(
                     Call3 {
-                        target: oracle.proxy_address.unwrap(),
+                        target: proxy,
                         allowFailure: true,
                         callData: datafeeds::contracts::EACAggregatorProxy::EACAggregatorProxy::phaseAggregatorsCall{ _0: i }.abi_encode().into(),
                     }
                 )
             };
-            match handle_multicall(provider, all_phases_calls).await {
-                Ok(res) => {
-                    res
-                        .into_iter()
-                        .map(|agg| 
-                            // check if correct?
-                            Address::abi_decode(&agg.returnData, false).unwrap()
-                        )
-                        .collect()
-                },
-                Err(e) => panic!("{}", e)
-            }
+            handle_multicall(provider, all_phases_calls, block)
+                .await?
+                .into_iter()
+                .map(|agg| 
+                    Address::abi_decode(&agg.returnData, false)
+                        .map_err(|e| eyre!("Cannot decode phase aggregator of {proxy}: {e}"))
+                )
+                .collect()
         },
-        Err(e) => panic!("{:#?}", e)   
+        Err(e) => Err(eyre!("Error fetching phase id for {proxy}: {:?}", e))
+    }
+}
+
+/// Returns the latest round id of every aggregator
+/// calls are made one by one: access controlled aggregators revert when read through the multicall contract
+pub async fn get_aggregators_latest_round(provider: Arc<ChainProvider>, aggregators: &[Address], block: Option<BlockId>) -> Vec<Option<u128>> {
+    let mut response = Vec::new();
+    for aggr in aggregators {
+        let tx = CallRequest {
+            to: Some(*aggr),
+            input: CallInput::new(datafeeds::contracts::EACAggregatorProxy::EACAggregatorProxy::latestRoundCall{}.abi_encode().into()),
+            ..Default::default()
+        };
+        match provider.call(tx, block).await {
+            Ok(r) => {
+                let round = U256::try_from_be_slice(&r).and_then(|r| u128::try_from(r).ok());
+                response.push(round);
+            },
+            Err(_) => response.push(None),
+        }
     }
+    response
+}
+
+/// Describes every phase of the proxy: aggregator, its version and the range of rounds it served
+pub async fn get_phases(provider: Arc<ChainProvider>, oracle: &Oracle, block: Option<BlockId>) -> Result<Vec<Phase>> {
+    let aggregators = get_aggregators(provider.clone(), oracle, block).await?;
+    let versions = get_aggregators_version(provider.clone(), aggregators.clone(), block).await?;
+    let latest_rounds = get_aggregators_latest_round(provider, &aggregators, block).await;
+    let phases = aggregators
+        .into_iter()
+        .zip(versions)
+        .zip(latest_rounds)
+        .enumerate()
+        .map(|(i, ((aggregator, version), last))| {
+            let id = i as u16 + 1;
+            Phase {
+                id,
+                aggregator,
+                version,
+                first_round: phase_round_id(id, 1),
+                last_round: last.map(|r| phase_round_id(id, r)),
+            }
+        })
+        .collect();
+    Ok(phases)
 }
 
 /// Returns addresses (Aggregator's) last round data
 /// this method is not working properly
-pub async fn get_aggregators_last_round_data(provider: Arc<Provider<Http<Client>>>, aggregators: Vec<Address>) -> Vec<Option<GetRoundDataReturn>> {
+pub async fn get_aggregators_last_round_data(provider: Arc<ChainProvider>, aggregators: Vec<Address>, block: Option<BlockId>) -> Result<Vec<Option<GetRoundDataReturn>>> {
     let all_last_round_queries: Vec<Call3> = aggregators
         .clone()
         .into_iter()
@@ -293,23 +617,138 @@ pub async fn get_aggregators_last_round_data(provider: Arc<Provider<Http<Client>
             }
         )
         .collect();
-    let mut response = Vec::new();
-    match handle_multicall(provider, all_last_round_queries).await {
-        Ok(resp) => {
-            for (result, aggr) in resp.into_iter().zip(aggregators) {
-                if result.success {
-                    if let Ok(res) = <GetRoundDataReturn as SolValue>::abi_decode(&result.returnData, false){
-                        response.push(Some(res));
-                    } else {
-                        response.push(None);
-                    }
-                } else {
-                    println!("Cannot retrieve timestamp for {}", aggr);
-                    response.push(None);
-                }
+    let response = handle_multicall(provider, all_last_round_queries, block)
+        .await?
+        .into_iter()
+        .map(|result|
+            if result.success {
+                <GetRoundDataReturn as SolValue>::abi_decode(&result.returnData, false).ok()
+            } else {
+                None
             }
-        },
-        Err(e) => panic!("{}", e)
-    };
-    response
+        )
+        .collect();
+    Ok(response)
+}
+
+/// Metadata read on a proxy, `None` for the calls that failed or returned nothing
+#[derive(Debug, Clone, Default)]
+pub struct ProxyMetadata {
+    pub description: Option<String>,
+    pub decimals: Option<u8>,
+    pub aggregator: Option<Address>,
+}
+
+/// Reads `description()`, `decimals()` and `aggregator()` of every proxy, in multicalls of `chunk_size` proxies.
+/// Results are in the same order as the proxies
+pub async fn get_proxies_metadata(
+    provider: Arc<ChainProvider>,
+    proxies: Vec<Address>,
+    chunk_size: usize,
+    block: Option<BlockId>,
+) -> Result<Vec<ProxyMetadata>> {
+    let mut all_metadata: Vec<ProxyMetadata> = Vec::new();
+    for chunk in proxies.chunks(chunk_size.max(1)) {
+        let calls: Vec<Call3> = chunk
+            .iter()
+            .flat_map(|proxy| [
+                datafeeds::contracts::EACAggregatorProxy::EACAggregatorProxy::descriptionCall{}.abi_encode(),
+                datafeeds::contracts::EACAggregatorProxy::EACAggregatorProxy::decimalsCall{}.abi_encode(),
+                datafeeds::contracts::EACAggregatorProxy::EACAggregatorProxy::aggregatorCall{}.abi_encode(),
+            ].map(|data| Call3 { target: *proxy, allowFailure: true, callData: data.into() }))
+            .collect();
+        let results = handle_multicall(provider.clone(), calls, block).await?;
+        for r in results.chunks(3) {
+            let data = |i: usize| r.get(i).filter(|r| r.success).map(|r| &r.returnData);
+            all_metadata.push(ProxyMetadata {
+                description: data(0).and_then(|d| String::abi_decode(d, false).ok()),
+                decimals: data(1).and_then(|d| u8::abi_decode(d, false).ok()),
+                aggregator: data(2).and_then(|d| Address::abi_decode(d, false).ok()),
+            });
+        }
+    }
+    Ok(all_metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn round(round_id: u128, updated_at: u64) -> GetRoundDataReturn {
+        GetRoundDataReturn {
+            roundId: round_id,
+            answer: I256::ZERO,
+            startedAt: U256::from(updated_at),
+            updatedAt: U256::from(updated_at),
+            answeredInRound: round_id,
+        }
+    }
+
+    /// Phases given as `(id, rounds)`, rounds updated every 100 seconds from 1000
+    fn feed(phases: &[(u16, u128)]) -> (Vec<Phase>, BTreeMap<u128, GetRoundDataReturn>) {
+        let mut rounds = BTreeMap::new();
+        let mut updated_at = 1000;
+        let phases = phases
+            .iter()
+            .map(|&(id, count)| {
+                for r in 1..=count {
+                    rounds.insert(phase_round_id(id, r), round(phase_round_id(id, r), updated_at));
+                    updated_at += 100;
+                }
+                Phase {
+                    id,
+                    aggregator: Address::ZERO,
+                    version: U256::from(4),
+                    first_round: phase_round_id(id, 1),
+                    last_round: Some(phase_round_id(id, count)),
+                }
+            })
+            .collect();
+        (phases, rounds)
+    }
+
+    async fn search(phases: &[Phase], rounds: &BTreeMap<u128, GetRoundDataReturn>, timestamp: u64) -> Option<(u16, u128)> {
+        let found = search_phases(phases, timestamp, |ids| {
+            let read: Vec<Option<GetRoundDataReturn>> = ids.iter().map(|id| rounds.get(id).cloned()).collect();
+            async move { Ok(read) }
+        }).await.unwrap();
+        found.map(|r| split_round_id(r.roundId))
+    }
+
+    #[test]
+    fn round_id_round_trip() {
+        assert_eq!(phase_round_id(5, 1), 92233720368547758081);
+        assert_eq!(split_round_id(92233720368547758081), (5, 1));
+        for (phase, round) in [(1, 0), (1, 1), (6, 12345), (u16::MAX, u64::MAX as u128)] {
+            assert_eq!(split_round_id(phase_round_id(phase, round)), (phase, round));
+        }
+    }
+
+    #[tokio::test]
+    async fn search_before_first_round() {
+        let (phases, rounds) = feed(&[(1, 50), (2, 200)]);
+        assert_eq!(search(&phases, &rounds, 0).await, None);
+        assert_eq!(search(&phases, &rounds, 999).await, None);
+    }
+
+    #[tokio::test]
+    async fn search_first_and_last_round_of_phase() {
+        let (phases, rounds) = feed(&[(1, 50), (2, 200)]);
+        // phase 1 is updated from 1000 to 5900, phase 2 from 6000 to 25900
+        assert_eq!(search(&phases, &rounds, 1000).await, Some((1, 1)));
+        assert_eq!(search(&phases, &rounds, 1099).await, Some((1, 1)));
+        assert_eq!(search(&phases, &rounds, 5900).await, Some((1, 50)));
+        assert_eq!(search(&phases, &rounds, 5999).await, Some((1, 50)));
+        assert_eq!(search(&phases, &rounds, 6000).await, Some((2, 1)));
+        assert_eq!(search(&phases, &rounds, 25900).await, Some((2, 200)));
+        assert_eq!(search(&phases, &rounds, 99999).await, Some((2, 200)));
+    }
+
+    #[tokio::test]
+    async fn search_inside_phase() {
+        let (phases, rounds) = feed(&[(1, 50), (2, 200)]);
+        assert_eq!(search(&phases, &rounds, 12345).await, Some((2, 64)));
+        assert_eq!(search(&phases, &rounds, 3050).await, Some((1, 21)));
+    }
 }
@@ -0,0 +1,174 @@
+use alloy_sol_types::{sol, SolEvent};
+use alloy_primitives::{utils::format_units, Address, B256, I256, U256};
+use alloy_providers::provider::TempProvider;
+use alloy_rpc_types::{Filter, Log};
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use std::{sync::Arc, time::Duration};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+use crate::functions::datafeeds::get_current_aggregator;
+use crate::provider::ChainProvider;
+use crate::types::{as_string, as_opt_string};
+use eyre::{Result, eyre};
+
+sol! {
+    event AnswerUpdated(int256 indexed current, uint256 indexed roundId, uint256 updatedAt);
+    event NewRound(uint256 indexed roundId, address indexed startedBy, uint256 startedAt);
+}
+
+/// Feed whose aggregator events are decoded
+#[derive(Debug, Clone)]
+pub struct EventSource {
+    pub pair: String,
+    pub proxy: Address,
+    pub decimals: u8,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum FeedEvent {
+    AnswerUpdated {
+        pair: String,
+        #[serde(serialize_with = "as_string")]
+        aggregator: Address,
+        #[serde(serialize_with = "as_string")]
+        round_id: U256,
+        #[serde(serialize_with = "as_string")]
+        answer: I256,
+        price: String,
+        #[serde(serialize_with = "as_string")]
+        updated_at: U256,
+        #[serde(serialize_with = "as_opt_string")]
+        block_number: Option<U256>,
+        #[serde(serialize_with = "as_opt_string")]
+        transaction_hash: Option<B256>,
+    },
+    NewRound {
+        pair: String,
+        #[serde(serialize_with = "as_string")]
+        aggregator: Address,
+        #[serde(serialize_with = "as_string")]
+        round_id: U256,
+        #[serde(serialize_with = "as_string")]
+        started_by: Address,
+        #[serde(serialize_with = "as_string")]
+        started_at: U256,
+        #[serde(serialize_with = "as_opt_string")]
+        block_number: Option<U256>,
+        #[serde(serialize_with = "as_opt_string")]
+        transaction_hash: Option<B256>,
+    },
+}
+
+/// Decodes an `AnswerUpdated` or `NewRound` log, other logs give `None`
+pub fn decode_log(source: &EventSource, log: &Log) -> Option<FeedEvent> {
+    match log.topics.first() {
+        Some(t) if *t == AnswerUpdated::SIGNATURE_HASH => {
+            let e = AnswerUpdated::decode_raw_log(log.topics.iter().copied(), &log.data, true).ok()?;
+            Some(FeedEvent::AnswerUpdated {
+                pair: source.pair.clone(),
+                aggregator: log.address,
+                round_id: e.roundId,
+                answer: e.current,
+                price: format_units(e.current, source.decimals).unwrap_or_default(),
+                updated_at: e.updatedAt,
+                block_number: log.block_number,
+                transaction_hash: log.transaction_hash,
+            })
+        },
+        Some(t) if *t == NewRound::SIGNATURE_HASH => {
+            let e = NewRound::decode_raw_log(log.topics.iter().copied(), &log.data, true).ok()?;
+            Some(FeedEvent::NewRound {
+                pair: source.pair.clone(),
+                aggregator: log.address,
+                round_id: e.roundId,
+                started_by: e.startedBy,
+                started_at: e.startedAt,
+                block_number: log.block_number,
+                transaction_hash: log.transaction_hash,
+            })
+        },
+        _ => None,
+    }
+}
+
+fn feed_events_filter(aggregator: Address) -> Filter {
+    Filter::new()
+        .address(aggregator)
+        .event_signature(vec![AnswerUpdated::SIGNATURE_HASH, NewRound::SIGNATURE_HASH])
+}
+
+/// Polls `eth_getLogs` on the current aggregator of the proxy every `interval`, starting at `from_block` (or the latest block).
+/// The aggregator is resolved again on each poll, so phase changes are followed
+pub async fn poll_events(
+    provider: Arc<ChainProvider>,
+    source: EventSource,
+    interval: Duration,
+    from_block: Option<u64>,
+    mut on_event: impl FnMut(FeedEvent),
+) -> Result<()> {
+    let mut next_block = match from_block {
+        Some(b) => b,
+        None => provider.get_block_number().await?.to::<u64>(),
+    };
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        let latest = provider.get_block_number().await?.to::<u64>();
+        if latest < next_block {
+            continue
+        }
+        let aggregator = get_current_aggregator(provider.clone(), source.proxy, None).await?;
+        let filter = feed_events_filter(aggregator)
+            .from_block(next_block)
+            .to_block(latest);
+        for log in provider.get_logs(filter).await? {
+            if let Some(event) = decode_log(&source, &log) {
+                on_event(event);
+            }
+        }
+        next_block = latest + 1;
+    }
+}
+
+/// Subscribes to the logs of the current aggregator with `eth_subscribe` over a WebSocket endpoint
+pub async fn subscribe_events(
+    provider: Arc<ChainProvider>,
+    ws_url: &str,
+    source: EventSource,
+    mut on_event: impl FnMut(FeedEvent),
+) -> Result<()> {
+    let aggregator = get_current_aggregator(provider, source.proxy, None).await?;
+    let (mut ws, _) = connect_async(ws_url).await?;
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "eth_subscribe",
+        "params": ["logs", feed_events_filter(aggregator)],
+    });
+    ws.send(Message::Text(request.to_string())).await?;
+    while let Some(message) = ws.next().await {
+        let text = match message? {
+            Message::Text(text) => text,
+            Message::Ping(data) => {
+                ws.send(Message::Pong(data)).await?;
+                continue
+            },
+            Message::Close(_) => break,
+            _ => continue,
+        };
+        let value: serde_json::Value = serde_json::from_str(&text)?;
+        if let Some(error) = value.get("error") {
+            return Err(eyre!("Subscription error: {}", error))
+        }
+        // the first message only carries the subscription id
+        let Some(result) = value.get("params").and_then(|p| p.get("result")) else {
+            continue
+        };
+        let log: Log = serde_json::from_value(result.clone())?;
+        if let Some(event) = decode_log(&source, &log) {
+            on_event(event);
+        }
+    }
+    Err(eyre!("WebSocket connection to {ws_url} closed"))
+}
@@ -1,3 +1,4 @@
 pub mod ccip;
 pub mod datafeeds;
+pub mod events;
 pub mod multicall3;
\ No newline at end of file
@@ -0,0 +1,148 @@
+use alloy_primitives::{I256, U256};
+use serde::Serialize;
+use crate::functions::datafeeds::GetRoundDataReturn;
+use crate::output::Render;
+use crate::types::RoundData;
+
+/// Limits used to judge the latest answer of a feed
+#[derive(Debug, Clone, Default)]
+pub struct HealthSettings {
+    /// seconds tolerated on top of the heartbeat
+    pub grace: u64,
+    /// maximum age of the answer, overrides the heartbeat of the index
+    pub max_age: Option<u64>,
+}
+
+/// Problems found in the latest round of a feed
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FeedIssue {
+    /// answer is older than the heartbeat (plus grace)
+    Stale { age: u64, max_age: u64 },
+    /// round was not answered in itself (`answeredInRound < roundId`)
+    IncompleteRound {
+        #[serde(serialize_with = "crate::types::as_string")]
+        round_id: u128,
+        #[serde(serialize_with = "crate::types::as_string")]
+        answered_in_round: u128,
+    },
+    /// round has no update timestamp
+    NotUpdated,
+    /// answer is zero or negative
+    NonPositiveAnswer,
+    /// answer moved past the deviation threshold but was only updated after the heartbeat (plus grace) expired
+    LaggedDeviation { deviation_pct: f64, threshold: f64, seconds: u64, max_age: u64 },
+}
+
+impl std::fmt::Display for FeedIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FeedIssue::Stale { age, max_age } => write!(f, "stale answer: updated {age}s ago, allowed {max_age}s"),
+            FeedIssue::IncompleteRound { round_id, answered_in_round } => write!(f, "incomplete round: round {round_id} answered in {answered_in_round}"),
+            FeedIssue::NotUpdated => write!(f, "round was never updated"),
+            FeedIssue::NonPositiveAnswer => write!(f, "answer is zero or negative"),
+            FeedIssue::LaggedDeviation { deviation_pct, threshold, seconds, max_age } => write!(f, "answer moved {deviation_pct:.4}% past the {threshold}% threshold but was updated after {seconds}s, allowed {max_age}s"),
+        }
+    }
+}
+
+/// Move of the latest answer from the previous round of the same phase
+#[derive(Debug, Clone, Serialize)]
+pub struct Deviation {
+    /// signed move, in percent of the previous answer
+    pub deviation_pct: f64,
+    /// seconds between both updates
+    pub seconds: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeedHealth {
+    pub chain: String,
+    #[serde(flatten)]
+    pub round: RoundData,
+    /// seconds since the last update
+    pub age: u64,
+    pub heartbeat: Option<u64>,
+    /// deviation threshold of the feed, in percent
+    pub threshold: Option<f64>,
+    /// move from the previous round, informative unless it is in `issues`
+    pub deviation: Option<Deviation>,
+    pub healthy: bool,
+    pub issues: Vec<FeedIssue>,
+}
+
+impl Render for FeedHealth {
+    fn text(&self) -> String {
+        let mut lines = vec![
+            format!("{} in [{}] is {} (round {})", self.round.pair, self.chain, self.round.price, self.round.round_id),
+            format!("updated {}s ago, heartbeat {}, deviation threshold {}",
+                self.age,
+                self.heartbeat.map_or("unknown".to_string(), |h| format!("{h}s")),
+                self.threshold.map_or("unknown".to_string(), |t| format!("{t}%")),
+            ),
+        ];
+        if let Some(d) = &self.deviation {
+            lines.push(format!("moved {:+.4}% from the previous round in {}s", d.deviation_pct, d.seconds));
+        }
+        if self.healthy {
+            lines.push("status: healthy".to_string());
+        } else {
+            lines.push("status: UNHEALTHY".to_string());
+            for issue in self.issues.iter() {
+                lines.push(format!("  - {}", issue));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+/// Checks a round against the heartbeat of its feed at time `now`
+pub fn check_round(round: &GetRoundDataReturn, heartbeat: Option<u64>, now: u64, settings: &HealthSettings) -> Vec<FeedIssue> {
+    let mut issues = Vec::new();
+    if round.updatedAt == U256::ZERO {
+        issues.push(FeedIssue::NotUpdated);
+    } else if let Some(max_age) = settings.max_age.or(heartbeat.map(|h| h + settings.grace)) {
+        let age = now.saturating_sub(round.updatedAt.to::<u64>());
+        if age > max_age {
+            issues.push(FeedIssue::Stale { age, max_age });
+        }
+    }
+    if round.answeredInRound < round.roundId {
+        issues.push(FeedIssue::IncompleteRound {
+            round_id: round.roundId,
+            answered_in_round: round.answeredInRound,
+        });
+    }
+    if round.answer <= I256::ZERO {
+        issues.push(FeedIssue::NonPositiveAnswer);
+    }
+    issues
+}
+
+/// Move from `previous` to `latest`, `None` when the previous round has no update or a zero answer
+pub fn round_deviation(previous: &GetRoundDataReturn, latest: &GetRoundDataReturn) -> Option<Deviation> {
+    if previous.updatedAt == U256::ZERO {
+        return None
+    }
+    let (previous_answer, latest_answer) = (previous.answer.to_string().parse::<f64>().ok()?, latest.answer.to_string().parse::<f64>().ok()?);
+    if previous_answer == 0.0 {
+        return None
+    }
+    Some(Deviation {
+        deviation_pct: (latest_answer - previous_answer) / previous_answer.abs() * 100.0,
+        seconds: latest.updatedAt.saturating_sub(previous.updatedAt).to::<u64>(),
+    })
+}
+
+/// Checks a move against the deviation threshold (in percent) of its feed.
+/// Moves past the threshold are what triggers updates, the feed is only flagged when such a move
+/// was reported after the heartbeat (plus grace) expired: the on-chain answer lagged behind it
+pub fn check_deviation(deviation: &Deviation, threshold: f64, heartbeat: Option<u64>, settings: &HealthSettings) -> Option<FeedIssue> {
+    let max_age = settings.max_age.or(heartbeat.map(|h| h + settings.grace))?;
+    (deviation.deviation_pct.abs() > threshold && deviation.seconds > max_age).then_some(FeedIssue::LaggedDeviation {
+        deviation_pct: deviation.deviation_pct,
+        threshold,
+        seconds: deviation.seconds,
+        max_age,
+    })
+}
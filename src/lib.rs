@@ -0,0 +1,19 @@
+pub mod cache;
+pub mod client;
+pub mod compare;
+pub mod config;
+pub mod constants;
+pub mod functions;
+pub mod health;
+pub mod output;
+pub mod provider;
+pub mod routes;
+pub mod search;
+pub mod sequencer;
+pub mod types;
+pub mod verify;
+pub mod wallet;
+pub mod watch;
+
+pub use client::FeedsClient;
+pub use types::{FeedInfo, Price, RoundData};
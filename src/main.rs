@@ -1,27 +1,91 @@
-pub mod constants;
-pub mod functions;
-
 use clap::{Subcommand, Parser};
 use ccip::{
     get_chain,
     get_router,
     get_selector,
     get_lane,
+    Lane,
 };
-use alloy_providers::provider::Provider;
-use alloy_transport_http::Http;
-use reqwest::Client;
-use alloy_rpc_client::ClientBuilder;
-use constants::get_provider_rpc_url;
 use alloy_chains::Chain;
-use eyre::Result;
-use std::{str::FromStr, sync::Arc};
 use datafeeds::OraclesIndex;
+use futures_util::future::join_all;
+use alloy_primitives::{utils::format_units, Address, Bytes, B256, U256};
+use alloy_rpc_types::{BlockId, BlockNumberOrTag};
+use eyre::Result;
+use std::{path::PathBuf, str::FromStr, sync::Arc, time::Duration};
+use alloy_providers::provider::TempProvider;
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use cli::FeedsClient;
+use cli::constants::{get_link_token, CCIP_SEARCH_WINDOW, DEFAULT_MAX_LEGS, DEFAULT_SEQUENCER_GRACE, L2_CHAINS, REFERENCE_FEED_CHAINS};
+use cli::client::expand_pairs;
+use cli::compare::{compare_chains, ChainQuote};
+use cli::search::{list_feeds, search_feeds, FeedEntry, FeedFilter};
+use cli::cache::IndexCache;
+use cli::config::Config;
+use cli::health::HealthSettings;
+use cli::watch::Watcher;
+use cli::functions::ccip::{
+    approve_calldata,
+    block_at_timestamp,
+    block_timestamp,
+    build_message,
+    ccip_send_calldata,
+    find_sent_message,
+    get_allowance,
+    get_fee,
+    get_fee_tokens,
+    get_lane_tokens,
+    get_message_status,
+    get_off_ramp_rate_limiter,
+    get_off_ramp_token_limiters,
+    get_on_ramp_rate_limiter,
+    get_sent_messages,
+    get_usd_value,
+    simulate_ccip_send,
+};
+use cli::functions::events::{self, FeedEvent};
+use cli::provider::{get_provider, ChainProvider, RetryPolicy};
+use cli::output::OutputFormat;
+use cli::types::{
+    CcipFee,
+    CcipSend,
+    CcipTransaction,
+    LaneInfo,
+    LaneToken,
+    LaneTokens,
+    MessageState,
+    RateLimit,
+    RouterInfo,
+    SelectorInfo,
+};
+use cli::wallet::{send_transaction, wallet_from_env};
+use alloy_signer::Signer;
 
 #[derive(Debug, Parser)]
 struct Cli {
     #[command(subcommand)]
     command: Option<Command>,
+    /// Format of the printed results
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+    /// RPC endpoints to use instead of the configured ones (comma separated for failover)
+    #[arg(long, global = true, value_delimiter(','))]
+    rpc_url: Vec<String>,
+    /// Retries of transient RPC errors, rotating across endpoints
+    #[arg(long, global = true, default_value_t = 3)]
+    max_retries: usize,
+    /// Print the RPC endpoint serving each call to stderr
+    #[arg(long, global = true)]
+    verbose: bool,
+    /// Block to read at: number, hash, latest, finalized or safe
+    #[arg(long, global = true, value_parser = parse_block_id)]
+    block: Option<BlockId>,
+    /// Config file (defaults to ~/.config/chainlink-cli/config.toml)
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    /// Use the cached feed indexes whatever their age, never download them
+    #[arg(long, global = true)]
+    offline: bool,
 }
 
 #[derive(Debug, Parser)] 
@@ -35,6 +99,66 @@ pub struct PairSetting {
 }
 // make a similar object for multiple inputs? (adds delimiter)
 
+/// Chains to compare a pair on, instead of a single `--chain`
+#[derive(Debug, Parser)]
+pub struct ChainsSetting {
+    /// query the pair concurrently on these chains and compare the answers
+    #[arg(long, value_delimiter(','), conflicts_with = "all_chains")]
+    pub chains: Vec<String>,
+    /// query the pair on every chain whose reference feeds list it
+    #[arg(long)]
+    pub all_chains: bool,
+}
+
+/// Filters of the feeds listed from the reference index
+#[derive(Debug, Parser)]
+pub struct FeedFilterSetting {
+    /// chains to look in (comma separated), defaults to every chain with reference feeds
+    #[arg(short, long, value_delimiter(','))]
+    pub chain: Vec<String>,
+    #[arg(short, long)]
+    pub base: Option<String>,
+    #[arg(short, long)]
+    pub quote: Option<String>,
+    /// feed category (low, medium, high, custom, new, deprecating)
+    #[arg(long)]
+    pub category: Option<String>,
+    #[arg(long)]
+    pub feed_type: Option<String>,
+    #[arg(long)]
+    pub decimals: Option<u8>,
+    /// only deprecated feeds (true) or only live ones (false)
+    #[arg(long)]
+    pub deprecated: Option<bool>,
+}
+
+impl FeedFilterSetting {
+    fn filter(&self) -> FeedFilter {
+        FeedFilter {
+            base: self.base.clone(),
+            quote: self.quote.clone(),
+            category: self.category.clone(),
+            feed_type: self.feed_type.clone(),
+            decimals: self.decimals,
+            deprecated: self.deprecated,
+        }
+    }
+
+    fn chains(&self) -> Vec<String> {
+        if self.chain.is_empty() {
+            REFERENCE_FEED_CHAINS.iter().map(|c| c.to_string()).collect()
+        } else {
+            self.chain.clone()
+        }
+    }
+}
+
+impl ChainsSetting {
+    pub fn is_set(&self) -> bool {
+        self.all_chains || !self.chains.is_empty()
+    }
+}
+
 #[derive(Debug, Subcommand)]
 enum Command {
     // Data feeds
@@ -42,17 +166,55 @@ enum Command {
         #[clap(flatten)]
         args: PairSetting,
     },
-    GetLatestAnswer {
+    /// Feeds of the reference index, no RPC needed
+    ListFeeds {
+        #[clap(flatten)]
+        filters: FeedFilterSetting,
+    },
+    /// Feeds whose name matches the query loosely ("eth usd", "wsteth"), best matches first
+    SearchFeeds {
+        query: String,
+        #[clap(flatten)]
+        filters: FeedFilterSetting,
+    },
+    /// Checks every feed of the index against its proxy on chain, exits with 1 when an entry is invalid
+    VerifyFeeds {
         #[arg(short, long)]
         chain: String,
+        /// print only the invalid entries
+        #[arg(long)]
+        only_invalid: bool,
+        /// proxies read per multicall
+        #[arg(long, default_value_t = 100)]
+        chunk_size: usize,
+    },
+    /// Latest answers: a single quote is reused for every base, lists of different length are combined.
+    /// Pairs without feed are derived through other feeds when possible
+    GetLatestAnswer {
+        #[arg(short, long, required_unless_present_any = ["chains", "all_chains"])]
+        chain: Option<String>,
         #[arg(short, long, value_delimiter(','))]
         base: Vec<String>,
         #[arg(short, long, value_delimiter(','))]
         quote: Vec<String>,
+        /// query every base/quote combination even when the lists have the same length
+        #[arg(short, long)]
+        matrix: bool,
+        #[clap(flatten)]
+        fan_out: ChainsSetting,
     },
     GetLatestRoundData {
+        #[arg(short, long, required_unless_present_any = ["chains", "all_chains"])]
+        chain: Option<String>,
+        #[arg(short, long)]
+        base: String,
+        #[arg(short, long)]
+        quote: String,
         #[clap(flatten)]
-        args: PairSetting,
+        fan_out: ChainsSetting,
+        /// seconds after an L2 sequencer restart during which prices are not trusted
+        #[arg(long, default_value_t = DEFAULT_SEQUENCER_GRACE)]
+        sequencer_grace: u64,
     },
     GetRoundData {
         #[arg(short, long)]
@@ -73,28 +235,234 @@ enum Command {
         #[clap(flatten)]
         args: PairSetting,
     },    
+    /// Price of the round that was current at a given time
+    GetPriceAt {
+        #[clap(flatten)]
+        args: PairSetting,
+        /// UNIX timestamp or ISO date (2024-01-31, 2024-01-31T12:00:00Z)
+        #[arg(short, long, value_parser = parse_timestamp)]
+        timestamp: u64,
+    },
+    /// Every round reported between two dates
+    GetHistory {
+        #[clap(flatten)]
+        args: PairSetting,
+        /// UNIX timestamp or ISO date
+        #[arg(short, long, value_parser = parse_timestamp)]
+        from: u64,
+        /// UNIX timestamp or ISO date, defaults to now
+        #[arg(short, long, value_parser = parse_timestamp)]
+        to: Option<u64>,
+        /// round ids read per multicall
+        #[arg(long, default_value_t = 100)]
+        chunk_size: usize,
+    },
+    /// Price derived through intermediate feeds (e.g. LINK/EUR = LINK/USD * 1/(EUR/USD))
+    GetCrossRate {
+        #[clap(flatten)]
+        args: PairSetting,
+        /// maximum number of feeds composed
+        #[arg(long, default_value_t = DEFAULT_MAX_LEGS)]
+        max_legs: usize,
+    },
+    /// Answer and round that were live at the block given with --block (needs an archive node)
+    PriceAtBlock {
+        #[clap(flatten)]
+        args: PairSetting,
+    },
+    /// Block in which a round was first reported, bisecting on block number (needs an archive node)
+    BlockOfRound {
+        #[clap(flatten)]
+        args: PairSetting,
+        #[arg(short, long)]
+        round_id: u128,
+        /// lower bound of the search, defaults to genesis
+        #[arg(long)]
+        from_block: Option<u64>,
+    },
+    /// Checks the latest answer for staleness, invalid rounds and threshold moves the feed was late to report, exits with 1 when unhealthy
+    CheckFeed {
+        #[clap(flatten)]
+        args: PairSetting,
+        /// seconds tolerated on top of the feed heartbeat
+        #[arg(long, default_value_t = 0)]
+        grace: u64,
+        /// maximum age of the answer in seconds, overrides the heartbeat
+        #[arg(long)]
+        max_age: Option<u64>,
+    },
+    /// Status of the L2 sequencer from its uptime feed, exits with 1 when prices should not be trusted
+    SequencerStatus {
+        #[arg(short, long)]
+        chain: String,
+        /// seconds after a restart during which prices are not trusted
+        #[arg(long, default_value_t = DEFAULT_SEQUENCER_GRACE)]
+        grace: u64,
+    },
+    /// Polls pairs and prints new rounds or price moves
+    Watch {
+        #[arg(short, long)]
+        chain: String,
+        #[arg(short, long, value_delimiter(','))]
+        base: Vec<String>,
+        #[arg(short, long, value_delimiter(','))]
+        quote: Vec<String>,
+        /// seconds between polls
+        #[arg(short, long, default_value_t = 12)]
+        interval: u64,
+        /// read the feeds only when a new block is seen (block number is polled every interval)
+        #[arg(long)]
+        every_block: bool,
+        /// only print when the price moved more than this percentage
+        #[arg(long)]
+        threshold: Option<f64>,
+    },
+    /// Streams AnswerUpdated and NewRound events of the feed aggregator as JSON lines
+    Subscribe {
+        #[clap(flatten)]
+        args: PairSetting,
+        /// WebSocket endpoint to use eth_subscribe, logs are polled over HTTP otherwise
+        #[arg(long)]
+        ws_url: Option<String>,
+        /// seconds between eth_getLogs polls
+        #[arg(short, long, default_value_t = 12)]
+        interval: u64,
+        /// first block to poll logs from, defaults to the latest
+        #[arg(long)]
+        from_block: Option<u64>,
+    },
 
 
     // CCIP
     GetRouter {chain: String},
     GetSelector {chain: String},
-//    GetFeeTokens {chain: String, selector: u8},
-/*     ChainStatus {
+    /// Fee tokens and transferable tokens of a lane, with the rate limiter of each token pool
+    GetLaneTokens {
+        #[arg(short, long)]
+        origin: String,
         #[arg(short, long)]
-        chain: String
-    }, */
+        destination: String,
+    },
     GetLane {
         #[arg(short, long)]
         origin: String,
         #[arg(short, long)]
         destination: String,
-    }
+    },
+    /// Rate limiters of a lane: onRamp and offRamp aggregates and the pool of every token on both sides.
+    /// --block applies to both chains, a tag (finalized, safe) is the portable choice
+    GetRateLimits {
+        #[arg(short, long)]
+        origin: String,
+        #[arg(short, long)]
+        destination: String,
+        /// only this token (source chain address)
+        #[arg(short, long)]
+        token: Option<Address>,
+        /// amount of the token to check, in token units. Its USD value is checked against the onRamp and offRamp limiters
+        #[arg(short, long, requires = "token")]
+        amount: Option<u128>,
+    },
+    /// Status of a CCIP message found from its source transaction or message id
+    CcipStatus {
+        #[arg(short, long)]
+        origin: String,
+        #[arg(short, long)]
+        destination: String,
+        /// source transaction hash
+        #[arg(long, required_unless_present = "message_id", conflicts_with = "message_id")]
+        tx: Option<B256>,
+        /// message id, searched in the onRamp logs
+        #[arg(long)]
+        message_id: Option<B256>,
+        /// first source block searched for the message id, defaults to the block of a week ago
+        #[arg(long)]
+        from_block: Option<u64>,
+        /// first destination block searched for the execution, defaults to the block at the time of sending
+        #[arg(long)]
+        dest_from_block: Option<u64>,
+        /// poll until every message is executed or failed
+        #[arg(short, long)]
+        wait: bool,
+        /// seconds between polls
+        #[arg(short, long, default_value_t = 30)]
+        interval: u64,
+    },
+    /// Sends a message (and tokens) through CCIP signing with PRIVATE_KEY, approving the tokens first
+    CcipSend {
+        #[arg(short, long)]
+        origin: String,
+        #[arg(short, long)]
+        destination: String,
+        /// EVM address receiving the message on the destination
+        #[arg(short, long)]
+        receiver: Address,
+        /// payload, hex encoded
+        #[arg(long, default_value = "0x")]
+        data: Bytes,
+        /// tokens to transfer as token:amount in token units, comma separated
+        #[arg(short, long, value_delimiter(','), value_parser = parse_token_amount)]
+        token: Vec<(Address, U256)>,
+        /// pay the fee in LINK instead of native
+        #[arg(long)]
+        pay_in_link: bool,
+        /// gas limit of the execution on the destination, the router default when not set
+        #[arg(long)]
+        gas_limit: Option<u64>,
+        /// only print the calldata, simulating ccipSend with eth_call when no approval is pending
+        #[arg(long)]
+        dry_run: bool,
+        /// sender simulated in a dry run without PRIVATE_KEY
+        #[arg(long)]
+        from: Option<Address>,
+    },
+    /// Fee to send a message (and tokens) through CCIP, in native and LINK
+    CcipFee {
+        #[arg(short, long)]
+        origin: String,
+        #[arg(short, long)]
+        destination: String,
+        /// EVM address receiving the message on the destination
+        #[arg(short, long)]
+        receiver: Address,
+        /// payload, hex encoded
+        #[arg(long, default_value = "0x")]
+        data: Bytes,
+        /// tokens to transfer as token:amount in token units, comma separated
+        #[arg(short, long, value_delimiter(','), value_parser = parse_token_amount)]
+        token: Vec<(Address, U256)>,
+        /// another fee token to quote, besides native and LINK
+        #[arg(long)]
+        fee_token: Option<Address>,
+        /// gas limit of the execution on the destination, the router default when not set
+        #[arg(long)]
+        gas_limit: Option<u64>,
+    },
+
+    /// Cached reference feed indexes
+    Feeds {
+        #[command(subcommand)]
+        action: FeedsCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum FeedsCommand {
+    /// Downloads the reference feeds of the chains into the cache
+    Update {
+        /// chains to update (comma separated), defaults to every chain with reference feeds
+        #[arg(short, long, value_delimiter(','))]
+        chain: Vec<String>,
+    },
 }
 
 #[tokio::main]
 async fn main() {
     let args = Cli::parse();
-    println!(r#"
+    let output = args.output;
+    // the banner goes to stderr, so streamed output (subscribe) stays parseable
+    if output.is_text() {
+        eprintln!(r#"
  _____ _           _       _     _____      _    
 /  __ \ |         (_)     | |   |_   _|    | |   
 | /  \/ |__   __ _ _ _ __ | |     | | _ __ | | __
@@ -102,130 +470,688 @@ async fn main() {
 | \__/\ | | | (_| | | | | | |_____| || | | |   < 
  \____/_| |_|\__,_|_|_| |_\_____/\___/_| |_|_|\_\
                                                  
-    "#);
+        "#);
+    }
     dotenv::dotenv().ok();
-    let rpc_url_id = std::env::var("RPC_URL_ID").expect("No RPC_URL_ID in .env");
+    let config = Config::load(args.config.as_deref()).expect("Error loading config");
+    let cache = config.index_cache(args.offline).expect("Error with feeds cache");
+    let rpc = RpcSettings {
+        config,
+        cache,
+        rpc_urls: args.rpc_url.clone(),
+        policy: RetryPolicy {
+            max_retries: args.max_retries,
+            verbose: args.verbose,
+            ..Default::default()
+        },
+        block: args.block,
+    };
     match &args.command {
         
         // Data Feeds
         Some(Command::GetOracle { args }) => {
-            let chain = Chain::from_str(&args.chain).expect("chain not found");
-            let datafeeds = OraclesIndex::load_reference_feeds(chain).await;
-            if let Some(oracle) = datafeeds.get_oracle(&args.base.to_uppercase(), &args.quote.to_uppercase()) { 
-                println!("{:#?}", oracle)
-            } else {
-                println!("No oracle found for {}/{} in {chain}", args.base, args.quote)
-            }
-        },
-        Some(Command::GetLatestAnswer { chain, base, quote }) => {
-            let chain = Chain::from_str(chain).expect("chain not found");
-            if base.len() == 0 || base.len() != quote.len() {  // TODO: if quote.len() == 1, reuse for all bases
-                panic!("Wrong input for token/base")
-            }
-            let datafeeds = OraclesIndex::load_reference_feeds(chain).await;
-            if let Ok(provider) = get_provider(chain, &rpc_url_id) {
-                match base.len() {
-                    1 => {
-                        let base = base.first().unwrap().to_uppercase();
-                        let quote = quote.first().unwrap().to_uppercase();
-                        if let Some(oracle) = datafeeds.get_oracle(&base, &quote) {
-                        let r = functions::datafeeds::get_latest_answer(provider, oracle.proxy_address.unwrap()).await.unwrap();
-                        println!("{}/{} in [{}] is {} [{}]",
-                            base, quote, chain, r,
-                            alloy_primitives::utils::format_units(r, oracle.decimals.unwrap()).unwrap()
-                        );
-                        }
-                    },
-                    _ => {
-                        let _ = functions::datafeeds::get_multiple_latest_answer(provider, chain, base.to_vec(), quote.to_vec()).await;
-                    },
-                }
-            }
+            let client = feeds_client(&args.chain, &rpc).await;
+            output.print_result(client.feed_info(&args.base, &args.quote));
+        },
+        Some(Command::ListFeeds { filters }) => {
+            let filter = filters.filter();
+            let feeds = feeds_across_chains(filters, &rpc.cache, |chain, index| list_feeds(chain, index, &filter)).await;
+            output.print(&feeds);
         },
-        Some(Command::GetLatestRoundData { args }) => {
-            let chain = Chain::from_str(&args.chain).expect(format!("chain not found for {}", args.chain).as_ref());
-            if let Ok(provider) = get_provider(chain, &rpc_url_id) {
-                let price_feeds = OraclesIndex::load_reference_feeds(chain).await;
-                if let Some(oracle) = price_feeds.get_oracle(&args.base.to_uppercase(), &args.quote.to_uppercase()) {
-                    let oracle_address= oracle.proxy_address.expect("This settings has no oracle defined");
-                    if let Ok(res) = functions::datafeeds::get_latest_round_data(provider, oracle_address).await {
-                        println!("{:?}", res)
-                    } else {
-                        println!("Error getting last round data for {}", oracle_address)
+        Some(Command::SearchFeeds { query, filters }) => {
+            let filter = filters.filter();
+            let feeds = feeds_across_chains(filters, &rpc.cache, |chain, index| search_feeds(chain, index, &filter, query)).await;
+            output.print(&feeds);
+        },
+        Some(Command::VerifyFeeds { chain, only_invalid, chunk_size }) => {
+            let client = feeds_client(chain, &rpc).await;
+            match client.verify_feeds(*chunk_size).await {
+                Ok(checks) => {
+                    let invalid = checks.iter().filter(|c| !c.valid).count();
+                    if output.is_text() {
+                        println!("{} feeds checked in [{}], {} invalid", checks.len(), client.chain(), invalid);
+                    }
+                    let checks: Vec<_> = checks.into_iter().filter(|c| !*only_invalid || !c.valid).collect();
+                    output.print(&checks);
+                    if invalid > 0 {
+                        std::process::exit(1);
                     }
+                },
+                Err(e) => {
+                    eprintln!("Error verifying feeds of {}: {}", client.chain(), e);
+                    std::process::exit(1);
                 }
             }
         },
-        Some(Command::GetDescription { args }) => {
-            let chain = Chain::from_str(&args.chain).expect("chain not found");
-            if let Ok(provider) = get_provider(chain, &rpc_url_id) {
-                let datafeeds = OraclesIndex::load_reference_feeds(chain).await;
-                if let Some(oracle) = datafeeds.get_oracle(&args.base.to_uppercase(), &args.quote.to_uppercase()) {                    
-                    functions::datafeeds::get_description(provider, oracle).await;
-                }
+        Some(Command::GetLatestAnswer { chain, base, quote, matrix, fan_out }) => {
+            let pairs = expand_pairs(base, quote, *matrix).expect("Wrong input for token/base");
+            if fan_out.is_set() {
+                let [(base, quote)] = pairs.as_slice() else {
+                    eprintln!("Comparing chains needs a single base/quote pair, got {}", pairs.len());
+                    std::process::exit(1);
+                };
+                output.print(&compare_across_chains(fan_out, base, quote, &rpc).await);
+                return
+            }
+            let client = feeds_client(chain.as_deref().expect("Missing --chain"), &rpc).await;
+            match pairs.as_slice() {
+                [(base, quote)] => output.print_result(client.latest_answer(base, quote, DEFAULT_MAX_LEGS).await),
+                _ => output.print_result(client.latest_prices(&pairs, DEFAULT_MAX_LEGS).await),
+            }
+        },
+        Some(Command::GetLatestRoundData { chain, base, quote, fan_out, sequencer_grace }) => {
+            if fan_out.is_set() {
+                output.print(&compare_across_chains(fan_out, base, quote, &rpc).await);
+                return
             }
+            let client = feeds_client(chain.as_deref().expect("Missing --chain"), &rpc).await;
+            output.print_result(client.latest_round_data_sequenced(base, quote, *sequencer_grace).await);
+        },
+        Some(Command::GetDescription { args }) => {
+            let client = feeds_client(&args.chain, &rpc).await;
+            output.print_result(client.description(&args.base, &args.quote).await);
         },
 
         // seems like multicall to the same Aggregator works fine, but mixed isn't
         Some(Command::GetRoundData { chain, base, quote, round_id }) => {
-            let chain = Chain::from_str(chain).expect("chain not found");
-            let base = base.to_uppercase();
-            let quote = quote.to_uppercase();
-            if let Ok(provider) = get_provider(chain, &rpc_url_id) {
-                let price_feeds = OraclesIndex::load_reference_feeds(chain).await;
-                if let Some(oracle) = price_feeds.get_oracle(&base, &quote) {
-                    match round_id.len() {
-                        1 => {
-                            let r = functions::datafeeds::get_round_data(provider, oracle.proxy_address.unwrap(), round_id.first().unwrap().to_owned()).await;
-                            println!("Round data for {}/{} [{}] in round-id {} \n{:?}",
-                                base, quote, chain, round_id.first().unwrap(), r
-                            );
-                        },
-                        _ => {
-                            let res = functions::datafeeds::get_multiple_round_data(provider, oracle.proxy_address.unwrap(), round_id.to_vec()).await;
-                            if let Ok(res) = res {
-                                for r in res {
-                                    println!("{:?}", r);
-                                }
-
-                            }
-                        },
+            let client = feeds_client(chain, &rpc).await;
+            match round_id.len() {
+                1 => output.print_result(client.round_data(base, quote, round_id[0]).await),
+                _ => output.print_result(client.rounds_data(base, quote, round_id).await),
+            }
+        },
+        Some(Command::GetAllPhases { args }) => {
+            let client = feeds_client(&args.chain, &rpc).await;
+            if output.is_text() {
+                println!("Phases for {}/{} in [{}]", args.base, args.quote, client.chain());
+            }
+            output.print_result(client.phases(&args.base, &args.quote).await);
+        },
+        Some(Command::GetPriceAt { args, timestamp }) => {
+            let client = feeds_client(&args.chain, &rpc).await;
+            match client.price_at(&args.base, &args.quote, *timestamp).await {
+                Ok(Some(r)) => {
+                    if output.is_text() {
+                        println!("{}/{} in [{}] at {}:", args.base, args.quote, client.chain(), timestamp);
+                    }
+                    output.print(&r);
+                },
+                Ok(None) => eprintln!("No round found for {}/{} before {}", args.base, args.quote, timestamp),
+                Err(e) => eprintln!("Error looking for round at {}: {}", timestamp, e)
+            }
+        },
+        Some(Command::GetHistory { args, from, to, chunk_size }) => {
+            let to = to.unwrap_or(chrono::Utc::now().timestamp() as u64);
+            let client = feeds_client(&args.chain, &rpc).await;
+            match client.history(&args.base, &args.quote, *from, to, *chunk_size).await {
+                Ok(history) => {
+                    if output.is_text() {
+                        println!("{} rounds for {}/{} in [{}] between {} and {}", history.len(), args.base, args.quote, client.chain(), from, to);
+                    }
+                    output.print(&history);
+                },
+                Err(e) => eprintln!("Error getting history for {}/{}: {}", args.base, args.quote, e)
+            }
+        },
+        Some(Command::GetCrossRate { args, max_legs }) => {
+            let client = feeds_client(&args.chain, &rpc).await;
+            output.print_result(client.cross_price(&args.base, &args.quote, *max_legs).await);
+        },
+        Some(Command::PriceAtBlock { args }) => {
+            let Some(BlockId::Number(BlockNumberOrTag::Number(block_number))) = rpc.block else {
+                eprintln!("price-at-block needs a block number in --block");
+                std::process::exit(1);
+            };
+            let client = feeds_client(&args.chain, &rpc).await;
+            output.print_result(client.price_at_block(&args.base, &args.quote, block_number.to::<u64>()).await);
+        },
+        Some(Command::BlockOfRound { args, round_id, from_block }) => {
+            let client = feeds_client(&args.chain, &rpc).await;
+            match client.block_of_round(&args.base, &args.quote, *round_id, *from_block).await {
+                Ok(Some(found)) => {
+                    if found.round.round_id != *round_id && output.is_text() {
+                        println!("Round {} was never the latest round, showing the next one reported", round_id);
                     }
+                    output.print(&found);
+                },
+                Ok(None) => eprintln!("Round {} not reported yet for {}/{}", round_id, args.base, args.quote),
+                Err(e) => eprintln!("Error looking for round {}: {}", round_id, e)
+            }
+        },
+        Some(Command::CheckFeed { args, grace, max_age }) => {
+            let client = feeds_client(&args.chain, &rpc).await;
+            let settings = HealthSettings { grace: *grace, max_age: *max_age };
+            match client.check_feed(&args.base, &args.quote, &settings).await {
+                Ok(health) => {
+                    output.print(&health);
+                    if !health.healthy {
+                        std::process::exit(1);
+                    }
+                },
+                Err(e) => {
+                    eprintln!("Error checking {}/{}: {}", args.base, args.quote, e);
+                    std::process::exit(1);
                 }
             }
         },
+        Some(Command::SequencerStatus { chain, grace }) => {
+            let client = feeds_client(chain, &rpc).await;
+            match client.sequencer_status(*grace).await {
+                Ok(Some(status)) => {
+                    output.print(&status);
+                    if !status.trusted {
+                        std::process::exit(1);
+                    }
+                },
+                Ok(None) if L2_CHAINS.contains(&client.chain().id()) => {
+                    eprintln!("No sequencer uptime feed known for the L2 {}, its status is unknown", client.chain());
+                    std::process::exit(1);
+                },
+                Ok(None) => eprintln!("No sequencer uptime feed known for {}", client.chain()),
+                Err(e) => {
+                    eprintln!("Error reading sequencer status of {}: {}", client.chain(), e);
+                    std::process::exit(1);
+                }
+            }
+        },
+        Some(Command::Watch { chain, base, quote, interval, every_block, threshold }) => {
+            let (base, quote): (Vec<String>, Vec<String>) = expand_pairs(base, quote, false)
+                .expect("Wrong input for token/base")
+                .into_iter()
+                .unzip();
+            let client = feeds_client(chain, &rpc).await;
+            let mut watcher = Watcher::new(*threshold);
+            let mut ticker = tokio::time::interval(Duration::from_secs(*interval));
+            let mut last_block = None;
+            loop {
+                ticker.tick().await;
+                if *every_block {
+                    match client.provider().get_block_number().await {
+                        Ok(block) if Some(block) == last_block => continue,
+                        Ok(block) => last_block = Some(block),
+                        Err(e) => {
+                            eprintln!("Error getting block number: {}", e);
+                            continue
+                        }
+                    }
+                }
+                match client.latest_rounds_data(&base, &quote).await {
+                    Ok(rounds) => {
+                        let changes = watcher.update(rounds);
+                        if !changes.is_empty() {
+                            output.print(&changes);
+                        }
+                    },
+                    Err(e) => eprintln!("Error polling feeds: {}", e)
+                }
+            }
+        },
+        Some(Command::Subscribe { args, ws_url, interval, from_block }) => {
+            let client = feeds_client(&args.chain, &rpc).await;
+            let source = client.event_source(&args.base, &args.quote).expect("Error resolving feed");
+            let print_event = |event: FeedEvent| println!("{}", serde_json::to_string(&event).unwrap());
+            let res = match ws_url {
+                Some(ws_url) => events::subscribe_events(client.provider(), ws_url, source, print_event).await,
+                None => events::poll_events(client.provider(), source, Duration::from_secs(*interval), *from_block, print_event).await,
+            };
+            if let Err(e) = res {
+                eprintln!("Error streaming events for {}/{}: {}", args.base, args.quote, e);
+                std::process::exit(1);
+            }
+        },
         //------------------------------------------------------------------------------//
         // CCIP
         Some(Command::GetRouter { chain }) => {
             let chain = get_chain(chain).expect("Error with chain selected");
             let router = get_router(&chain).expect("Error looking for router");
-            println!("Router for {} is {}", chain, format!("{}", router));
+            output.print(&RouterInfo { chain: chain.to_string(), router: router.to_string() });
         },
         Some(Command::GetSelector { chain }) => {
             let chain = get_chain(chain).expect("Error with chain selected");
             let selector = get_selector(&chain).expect("Error looking for router");
-            println!("Selector for {} is {}", chain, selector);
+            output.print(&SelectorInfo { chain: chain.to_string(), selector: selector.to_string() });
         },
         Some(Command::GetLane { origin, destination }) => {
             let chain_s = get_chain(&origin).expect("Error with source");
             let chain_d = get_chain(&destination).expect("Error with destination");
             let lane = get_lane(chain_s, chain_d).expect("Error looking for lane");
-            println!("{:#?}", lane);
-        },
-        /* Some(Command::ChainStatus { chain_name }) => {
-            let chain = get_chain(chain_name).expect("Error with chain selected");
-            let pk = dotenv::var("PRIVATE_KEY").expect("No private key supplied to .env");         
-            get_status_on_chain(pk, chain).await.expect("Error getting status");
-            //println!("{:#?}", user_status);
-        }, */
+            output.print(&LaneInfo {
+                origin: origin.to_string(),
+                destination: destination.to_string(),
+                on_ramp: lane.on_ramp,
+                off_ramp: lane.off_ramp,
+                commit_store: lane.commit_store,
+            });
+        },
+        Some(Command::GetLaneTokens { origin, destination }) => {
+            let route = ccip_route(origin, destination);
+            let provider = chain_provider(route.chain, &rpc).expect("Error building provider");
+            let fee_tokens = get_fee_tokens(provider.clone(), route.lane.on_ramp, rpc.block).await;
+            let tokens = get_lane_tokens(provider, route.lane.on_ramp, route.selector, rpc.block).await;
+            match (fee_tokens, tokens) {
+                (Ok(fee_tokens), Ok(tokens)) => output.print(&LaneTokens {
+                    origin: origin.to_string(),
+                    destination: destination.to_string(),
+                    on_ramp: route.lane.on_ramp,
+                    fee_tokens: fee_tokens.iter().map(|t| t.to_string()).collect(),
+                    tokens,
+                }),
+                (Err(e), _) | (_, Err(e)) => eprintln!("Error reading tokens of lane {} -> {}: {}", origin, destination, e),
+            }
+        },
+        Some(Command::GetRateLimits { origin, destination, token, amount }) => {
+            let route = ccip_route(origin, destination);
+            let source = chain_provider(route.chain, &rpc).expect("Error building source provider");
+            let dest = chain_provider(route.destination, &rpc).expect("Error building destination provider");
+            let on_ramp = get_on_ramp_rate_limiter(source.clone(), route.lane.on_ramp, rpc.block).await;
+            let off_ramp = get_off_ramp_rate_limiter(dest.clone(), route.lane.off_ramp, rpc.block).await;
+            let tokens: Vec<LaneToken> = get_lane_tokens(source.clone(), route.lane.on_ramp, route.selector, rpc.block)
+                .await
+                .expect("Error reading lane tokens")
+                .into_iter()
+                .filter(|t| token.map_or(true, |token| t.token == token))
+                .collect();
+            let source_tokens: Vec<Address> = tokens.iter().map(|t| t.token).collect();
+            let dest_limiters = get_off_ramp_token_limiters(dest, route.lane.off_ramp, &source_tokens, rpc.block)
+                .await
+                .expect("Error reading destination pools");
+            // the aggregate limiters count the USD value of the transfer (18 decimals)
+            let usd_amount = match (token, amount) {
+                (Some(token), Some(amount)) => get_usd_value(source.clone(), route.lane.on_ramp, *token, *amount, rpc.block)
+                    .await
+                    .map_err(|e| eprintln!("Error pricing the amount, aggregate limiters not checked: {}", e))
+                    .ok(),
+                _ => None,
+            };
+            let mut limits = vec![
+                RateLimit::new("on_ramp", None, None, on_ramp.map_err(|e| eprintln!("Error reading onRamp limiter: {}", e)).ok(), usd_amount),
+                RateLimit::new("off_ramp", None, None, off_ramp.map_err(|e| eprintln!("Error reading offRamp limiter: {}", e)).ok(), usd_amount),
+            ];
+            for (t, dest_limiter) in tokens.into_iter().zip(dest_limiters) {
+                limits.push(RateLimit::new("source_pool", Some(t.token), t.symbol.clone(), t.rate_limiter, *amount));
+                limits.push(RateLimit::new("destination_pool", Some(t.token), t.symbol, dest_limiter, *amount));
+            }
+            if let (Some(token), 2) = (token, limits.len()) {
+                eprintln!("Token {} is not supported on lane {} -> {}", token, origin, destination);
+            }
+            output.print(&limits);
+        },
+        Some(Command::CcipStatus { origin, destination, tx, message_id, from_block, dest_from_block, wait, interval }) => {
+            let route = ccip_route(origin, destination);
+            let source = chain_provider(route.chain, &rpc).expect("Error building source provider");
+            let dest = chain_provider(route.destination, &rpc).expect("Error building destination provider");
+            let messages = match (tx, message_id) {
+                (Some(tx), _) => get_sent_messages(source.clone(), route.lane.on_ramp, *tx).await,
+                (None, Some(message_id)) => {
+                    let from_block = match from_block {
+                        Some(b) => *b,
+                        None => {
+                            let since = (chrono::Utc::now().timestamp() as u64).saturating_sub(CCIP_SEARCH_WINDOW);
+                            block_at_timestamp(source.clone(), since).await.expect("Error looking for the first source block")
+                        },
+                    };
+                    find_sent_message(source.clone(), route.lane.on_ramp, *message_id, from_block).await.map(|m| m.into_iter().collect())
+                },
+                (None, None) => unreachable!("clap requires --tx or --message-id"),
+            }.expect("Error looking for the message");
+            if messages.is_empty() {
+                eprintln!("No CCIP message found on lane {} -> {}", origin, destination);
+                std::process::exit(1);
+            }
+            // the execution comes after the message was sent, searched from the time of the source block
+            let dest_from_block = match (dest_from_block, messages.iter().filter_map(|m| m.source_block).min()) {
+                (Some(b), _) => *b,
+                (None, Some(source_block)) => {
+                    let sent_at = block_timestamp(source.clone(), source_block).await.expect("Error reading the source block");
+                    block_at_timestamp(dest.clone(), sent_at).await.expect("Error looking for the first destination block")
+                },
+                (None, None) => {
+                    let since = (chrono::Utc::now().timestamp() as u64).saturating_sub(CCIP_SEARCH_WINDOW);
+                    block_at_timestamp(dest.clone(), since).await.expect("Error looking for the first destination block")
+                },
+            };
+            let mut ticker = tokio::time::interval(Duration::from_secs(*interval));
+            let mut last_states = Vec::new();
+            loop {
+                ticker.tick().await;
+                let mut statuses = Vec::new();
+                for message in messages.iter() {
+                    match get_message_status(dest.clone(), route.lane.commit_store, route.lane.off_ramp, message, dest_from_block).await {
+                        Ok(status) => statuses.push(status),
+                        Err(e) => eprintln!("Error reading status of {}: {}", message.message_id, e),
+                    }
+                }
+                let states: Vec<MessageState> = statuses.iter().map(|s| s.state).collect();
+                let done = states.len() == messages.len() && states.iter().all(|s| s.is_final());
+                if states != last_states || !*wait {
+                    output.print(&statuses);
+                }
+                if done || !*wait {
+                    if states.contains(&MessageState::Failed) {
+                        std::process::exit(1);
+                    }
+                    break
+                }
+                last_states = states;
+            }
+        },
+        Some(Command::CcipSend { origin, destination, receiver, data, token, pay_in_link, gas_limit, dry_run, from }) => {
+            let route = ccip_route(origin, destination);
+            let provider = chain_provider(route.chain, &rpc).expect("Error building provider");
+            let wallet = wallet_from_env();
+            let sender = match (&wallet, from) {
+                (Ok(wallet), _) => wallet.address(),
+                (Err(_), Some(from)) if *dry_run => *from,
+                (Err(e), _) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                },
+            };
+            let fee_token = match pay_in_link {
+                true => get_link_token(route.chain.id())
+                    .expect("No LINK token known for the origin chain")
+                    .parse::<Address>()
+                    .expect("Invalid LINK address"),
+                false => Address::ZERO,
+            };
+            let message = build_message(*receiver, data.clone(), token.clone(), fee_token, *gas_limit);
+            let fee = get_fee(provider.clone(), route.router, route.selector, message.clone(), None)
+                .await
+                .expect("Error getting fee");
+            // amount the router takes of each token, the fee included when paid in LINK
+            let mut spends: Vec<(Address, U256)> = Vec::new();
+            for (t, amount) in token.iter().copied().chain((fee_token != Address::ZERO).then_some((fee_token, fee))) {
+                match spends.iter_mut().find(|(s, _)| *s == t) {
+                    Some((_, total)) => *total += amount,
+                    None => spends.push((t, amount)),
+                }
+            }
+            let mut transactions = Vec::new();
+            for (t, amount) in spends {
+                let allowance = get_allowance(provider.clone(), t, sender, route.router).await.expect("Error reading allowance");
+                if allowance < amount {
+                    transactions.push((format!("approve {}", t), t, U256::ZERO, approve_calldata(route.router, amount)));
+                }
+            }
+            let value = if fee_token == Address::ZERO { fee } else { U256::ZERO };
+            transactions.push(("ccipSend".to_string(), route.router, value, ccip_send_calldata(route.selector, message)));
+            let mut result = CcipSend {
+                origin: origin.to_string(),
+                destination: destination.to_string(),
+                from: sender,
+                dry_run: *dry_run,
+                fee_token: if fee_token == Address::ZERO { "native".to_string() } else { fee_token.to_string() },
+                fee,
+                message_id: None,
+                simulation: None,
+                transactions: Vec::new(),
+            };
+            if *dry_run {
+                // eth_call cannot apply the approvals first, ccipSend would revert for lack of allowance
+                let approvals = transactions.len() - 1;
+                if approvals > 0 {
+                    result.simulation = Some(format!("ccipSend not simulated, {} approval(s) pending", approvals));
+                } else {
+                    let (_, to, value, calldata) = transactions.last().cloned().expect("ccipSend is always present");
+                    match simulate_ccip_send(provider, sender, to, value, calldata).await {
+                        Ok(message_id) => result.message_id = Some(message_id),
+                        Err(e) => result.simulation = Some(e.to_string()),
+                    }
+                }
+                result.transactions = transactions
+                    .into_iter()
+                    .map(|(action, to, value, calldata)| CcipTransaction { action, to, value, calldata: calldata.to_string(), tx_hash: None })
+                    .collect();
+                output.print(&result);
+                return
+            }
+            let wallet = wallet.expect("Error loading wallet");
+            for (action, to, value, calldata) in transactions {
+                let tx_hash = send_transaction(provider.clone(), &wallet, route.chain.id(), to, value, calldata.clone())
+                    .await
+                    .expect(format!("Error sending {}", action).as_ref());
+                if action == "ccipSend" {
+                    result.message_id = get_sent_messages(provider.clone(), route.lane.on_ramp, tx_hash)
+                        .await
+                        .expect("Error reading the sent message")
+                        .first()
+                        .map(|m| m.message_id);
+                }
+                result.transactions.push(CcipTransaction {
+                    action,
+                    to,
+                    value,
+                    calldata: calldata.to_string(),
+                    tx_hash: Some(tx_hash),
+                });
+            }
+            output.print(&result);
+        },
+        Some(Command::CcipFee { origin, destination, receiver, data, token, fee_token, gas_limit }) => {
+            let route = ccip_route(origin, destination);
+            let provider = chain_provider(route.chain, &rpc).expect("Error building provider");
+            let mut fee_tokens = vec![("native".to_string(), Address::ZERO)];
+            if let Some(link) = get_link_token(route.chain.id()) {
+                fee_tokens.push(("LINK".to_string(), link.parse().expect("Invalid LINK address")));
+            }
+            if let Some(fee_token) = fee_token {
+                fee_tokens.push((fee_token.to_string(), *fee_token));
+            }
+            let mut fees = Vec::new();
+            for (name, fee_token) in fee_tokens {
+                let message = build_message(*receiver, data.clone(), token.clone(), fee_token, *gas_limit);
+                match get_fee(provider.clone(), route.router, route.selector, message, rpc.block).await {
+                    Ok(raw) => fees.push(CcipFee {
+                        origin: origin.to_string(),
+                        destination: destination.to_string(),
+                        fee: matches!(name.as_str(), "native" | "LINK").then(|| format_units(raw, 18).unwrap_or_default()),
+                        fee_token: name,
+                        raw,
+                    }),
+                    Err(e) => eprintln!("Error getting fee in {}: {}", name, e),
+                }
+            }
+            output.print(&fees);
+        },
+        //------------------------------------------------------------------------------//
+        Some(Command::Feeds { action: FeedsCommand::Update { chain } }) => {
+            let chains: Vec<String> = if chain.is_empty() {
+                REFERENCE_FEED_CHAINS.iter().map(|c| c.to_string()).collect()
+            } else {
+                chain.clone()
+            };
+            for name in chains {
+                let chain = Chain::from_str(&name).expect(format!("chain not found for {}", name).as_ref());
+                output.print_result(rpc.cache.update(chain).await);
+            }
+        },
         _ => println!("Command unknown"),
     }
 }
 
-pub fn get_provider(chain: Chain, rpc_url_id: &str) -> Result<Arc<Provider<Http<Client>>>> {
-// get_provider(chain)
-    let rpc_url = get_provider_rpc_url(chain.id(), rpc_url_id).expect("No RPC URL found for {chain}");
-    let client = ClientBuilder::default().reqwest_http(rpc_url.parse()?);
-    let provider = Provider::new_with_client(client);
-    Ok(Arc::new(provider))
-}
\ No newline at end of file
+/// Where to find the RPC endpoints and the feed index of each chain
+struct RpcSettings {
+    config: Config,
+    cache: IndexCache,
+    rpc_urls: Vec<String>,
+    policy: RetryPolicy,
+    block: Option<BlockId>,
+}
+
+/// Builds the data feeds client for a chain name
+async fn feeds_client(chain: &str, rpc: &RpcSettings) -> FeedsClient {
+    let chain = Chain::from_str(chain).expect(format!("chain not found for {}", chain).as_ref());
+    let index = rpc.cache.load(chain).await.expect("Error loading reference feeds");
+    client_with_index(chain, index, rpc).expect("Error building provider")
+}
+
+fn client_with_index(chain: Chain, index: OraclesIndex, rpc: &RpcSettings) -> Result<FeedsClient> {
+    let provider = chain_provider(chain, rpc)?;
+    Ok(FeedsClient::with_index(chain, provider, index).at_block(rpc.block))
+}
+
+fn chain_provider(chain: Chain, rpc: &RpcSettings) -> Result<Arc<ChainProvider>> {
+    let urls = rpc.config.resolve_rpc_urls(chain, &rpc.rpc_urls)?;
+    get_provider(&urls, rpc.policy.clone())
+}
+
+/// Chains, lane, router of the origin chain and selector of the destination chain
+struct CcipRoute {
+    chain: Chain,
+    destination: Chain,
+    router: Address,
+    selector: u64,
+    lane: Lane,
+}
+
+fn ccip_route(origin: &str, destination: &str) -> CcipRoute {
+    let chain_o = get_chain(origin).expect("Error with origin");
+    let chain_d = get_chain(destination).expect("Error with destination");
+    CcipRoute {
+        chain: Chain::from_str(origin).expect(format!("chain not found for {}", origin).as_ref()),
+        destination: Chain::from_str(destination).expect(format!("chain not found for {}", destination).as_ref()),
+        router: get_router(&chain_o)
+            .expect("Error looking for router")
+            .to_string()
+            .parse()
+            .expect("Invalid router address"),
+        selector: get_selector(&chain_d)
+            .expect("Error looking for selector")
+            .to_string()
+            .parse()
+            .expect("Invalid chain selector"),
+        lane: get_lane(chain_o, chain_d).expect("Error looking for lane"),
+    }
+}
+
+/// Feeds selected by `select` in the reference index of every chain of the filter, indexes are loaded concurrently
+async fn feeds_across_chains(filters: &FeedFilterSetting, cache: &IndexCache, select: impl Fn(&str, &OraclesIndex) -> Vec<FeedEntry>) -> Vec<FeedEntry> {
+    let chains: Vec<Chain> = filters
+        .chains()
+        .iter()
+        .map(|c| Chain::from_str(c).expect(format!("chain not found for {}", c).as_ref()))
+        .collect();
+    let indexes = join_all(chains.iter().map(|c| cache.load(*c))).await;
+    chains
+        .iter()
+        .zip(indexes)
+        .flat_map(|(chain, index)| match index {
+            Ok(index) => select(&chain.to_string(), &index),
+            Err(e) => {
+                eprintln!("{}", e);
+                Vec::new()
+            }
+        })
+        .collect()
+}
+
+/// Latest round of the pair read concurrently on several chains, compared to the median.
+/// With `--all-chains` the chains whose reference feeds don't list the pair are left out
+async fn compare_across_chains(fan_out: &ChainsSetting, base: &str, quote: &str, rpc: &RpcSettings) -> Vec<ChainQuote> {
+    let names: Vec<String> = if fan_out.all_chains {
+        REFERENCE_FEED_CHAINS.iter().map(|c| c.to_string()).collect()
+    } else {
+        fan_out.chains.clone()
+    };
+    let reads = names.into_iter().map(|name| async move {
+        let chain = match Chain::from_str(&name) {
+            Ok(chain) => chain,
+            Err(_) => return Some((name.clone(), Err(eyre::eyre!("chain not found for {}", name)))),
+        };
+        let index = match rpc.cache.load(chain).await {
+            Ok(index) => index,
+            Err(_) if fan_out.all_chains => return None,
+            Err(e) => return Some((chain.to_string(), Err(e))),
+        };
+        if fan_out.all_chains && index.get_oracle(&base.to_uppercase(), &quote.to_uppercase()).is_none() {
+            return None
+        }
+        let round = match client_with_index(chain, index, rpc) {
+            Ok(client) => client.latest_round_data(base, quote).await,
+            Err(e) => Err(e),
+        };
+        Some((chain.to_string(), round))
+    });
+    let results = join_all(reads).await.into_iter().flatten().collect();
+    compare_chains(&format!("{}/{}", base.to_uppercase(), quote.to_uppercase()), results)
+}
+
+/// Parses a UNIX timestamp or an ISO date (defaults to UTC)
+pub fn parse_timestamp(input: &str) -> Result<u64> {
+    if let Ok(ts) = input.parse::<u64>() {
+        return Ok(ts)
+    }
+    let timestamp = if let Ok(dt) = DateTime::parse_from_rfc3339(input) {
+        dt.timestamp()
+    } else if let Ok(dt) = NaiveDateTime::parse_from_str(input, "%Y-%m-%dT%H:%M:%S") {
+        dt.and_utc().timestamp()
+    } else if let Ok(d) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        d.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp()
+    } else {
+        return Err(eyre::eyre!("Cannot parse {input} as timestamp or date"))
+    };
+    // dates before 1970 have negative timestamps
+    u64::try_from(timestamp).map_err(|_| eyre::eyre!("{input} is before the UNIX epoch"))
+}
+
+/// Parses a token amount given as `token:amount`
+pub fn parse_token_amount(input: &str) -> Result<(Address, U256)> {
+    let (token, amount) = input
+        .split_once(':')
+        .ok_or(eyre::eyre!("Token amount {input} should be token:amount"))?;
+    Ok((token.parse()?, amount.parse()?))
+}
+
+/// Parses a block number, block hash or tag (latest, finalized, safe, earliest, pending)
+pub fn parse_block_id(input: &str) -> Result<BlockId> {
+    let block = match input.to_lowercase().as_str() {
+        "latest" => BlockNumberOrTag::Latest.into(),
+        "finalized" => BlockNumberOrTag::Finalized.into(),
+        "safe" => BlockNumberOrTag::Safe.into(),
+        "earliest" => BlockNumberOrTag::Earliest.into(),
+        "pending" => BlockNumberOrTag::Pending.into(),
+        hash if hash.starts_with("0x") && hash.len() == 66 => B256::from_str(hash)?.into(),
+        number if number.starts_with("0x") => u64::from_str_radix(&number[2..], 16)?.into(),
+        number => number.parse::<u64>()?.into(),
+    };
+    Ok(block)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamp_unix_seconds() {
+        assert_eq!(parse_timestamp("0").unwrap(), 0);
+        assert_eq!(parse_timestamp("1705276800").unwrap(), 1705276800);
+    }
+
+    #[test]
+    fn timestamp_dates() {
+        assert_eq!(parse_timestamp("2024-01-15").unwrap(), 1705276800);
+        assert_eq!(parse_timestamp("1970-01-01").unwrap(), 0);
+        // without offset the time is UTC
+        assert_eq!(parse_timestamp("2024-01-15T12:30:00").unwrap(), 1705321800);
+    }
+
+    #[test]
+    fn timestamp_rfc3339() {
+        assert_eq!(parse_timestamp("2024-01-15T12:30:00Z").unwrap(), 1705321800);
+        assert_eq!(parse_timestamp("2024-01-15T12:30:00+02:00").unwrap(), 1705314600);
+    }
+
+    #[test]
+    fn timestamp_pre_epoch() {
+        assert!(parse_timestamp("1969-12-31").is_err());
+        assert!(parse_timestamp("1969-12-31T23:59:59Z").is_err());
+        assert!(parse_timestamp("-1").is_err());
+    }
+
+    #[test]
+    fn timestamp_invalid() {
+        assert!(parse_timestamp("").is_err());
+        assert!(parse_timestamp("yesterday").is_err());
+        assert!(parse_timestamp("2024-13-01").is_err());
+    }
+}
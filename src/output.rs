@@ -0,0 +1,145 @@
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// How command results are printed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// human readable lines
+    #[default]
+    Text,
+    Json,
+    Csv,
+    Table,
+}
+
+/// Results that can be printed in every output format
+pub trait Render: Serialize {
+    fn text(&self) -> String;
+}
+
+impl<T: Render> Render for Vec<T> {
+    fn text(&self) -> String {
+        self
+            .iter()
+            .map(|i| i.text())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+impl OutputFormat {
+    pub fn is_text(&self) -> bool {
+        *self == OutputFormat::Text
+    }
+
+    pub fn print<T: Render>(&self, value: &T) {
+        println!("{}", self.render(value))
+    }
+
+    /// Prints the result, or the error to stderr
+    pub fn print_result<T: Render>(&self, result: eyre::Result<T>) {
+        match result {
+            Ok(value) => self.print(&value),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    pub fn render<T: Render>(&self, value: &T) -> String {
+        match self {
+            OutputFormat::Text => value.text(),
+            OutputFormat::Json => serde_json::to_string_pretty(value).expect("Error serializing result"),
+            OutputFormat::Csv => {
+                let (headers, rows) = tabulate(value);
+                let mut lines = vec![headers.iter().map(|h| csv_field(h)).collect::<Vec<String>>().join(",")];
+                for row in rows {
+                    lines.push(row.iter().map(|f| csv_field(f)).collect::<Vec<String>>().join(","));
+                }
+                lines.join("\n")
+            },
+            OutputFormat::Table => {
+                let (headers, rows) = tabulate(value);
+                let widths: Vec<usize> = headers
+                    .iter()
+                    .enumerate()
+                    .map(|(i, h)| rows.iter().map(|r| r[i].len()).chain([h.len()]).max().unwrap_or(0))
+                    .collect();
+                let line = |fields: &[String]| fields
+                    .iter()
+                    .zip(&widths)
+                    .map(|(f, w)| format!("{:<w$}", f, w = w))
+                    .collect::<Vec<String>>()
+                    .join(" | ");
+                let mut lines = vec![
+                    line(&headers),
+                    widths.iter().map(|w| "-".repeat(*w)).collect::<Vec<String>>().join("-+-"),
+                ];
+                for row in rows {
+                    lines.push(line(&row));
+                }
+                lines.join("\n")
+            },
+        }
+    }
+}
+
+/// Turns a result (or list of results) into a header and rows, nested fields are flattened as `parent.child`
+fn tabulate<T: Serialize>(value: &T) -> (Vec<String>, Vec<Vec<String>>) {
+    let records: Vec<Map<String, Value>> = match serde_json::to_value(value).expect("Error serializing result") {
+        Value::Array(items) => items.into_iter().map(flatten).collect(),
+        other => vec![flatten(other)],
+    };
+    let mut headers: Vec<String> = Vec::new();
+    for record in records.iter() {
+        for key in record.keys() {
+            if !headers.contains(key) {
+                headers.push(key.clone());
+            }
+        }
+    }
+    let rows = records
+        .iter()
+        .map(|record| headers
+            .iter()
+            .map(|h| match record.get(h) {
+                None | Some(Value::Null) => String::new(),
+                Some(Value::String(s)) => s.clone(),
+                Some(v) => v.to_string(),
+            })
+            .collect()
+        )
+        .collect();
+    (headers, rows)
+}
+
+fn flatten(value: Value) -> Map<String, Value> {
+    let mut flat = Map::new();
+    match value {
+        Value::Object(map) => {
+            for (key, v) in map {
+                match v {
+                    Value::Object(_) => {
+                        for (inner, iv) in flatten(v) {
+                            flat.insert(format!("{key}.{inner}"), iv);
+                        }
+                    },
+                    v => {
+                        flat.insert(key, v);
+                    }
+                }
+            }
+        },
+        other => {
+            flat.insert("value".to_string(), other);
+        }
+    }
+    flat
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
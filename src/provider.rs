@@ -0,0 +1,233 @@
+use alloy_json_rpc::{RequestPacket, ResponsePacket, ResponsePayload};
+use alloy_providers::provider::Provider;
+use alloy_rpc_client::ClientBuilder;
+use alloy_transport::{TransportError, TransportErrorKind, TransportFut};
+use eyre::{eyre, Result};
+use reqwest::{header::CONTENT_TYPE, Client, StatusCode, Url};
+use std::{
+    sync::{atomic::{AtomicUsize, Ordering}, Arc},
+    task::{Context, Poll},
+    time::Duration,
+};
+use tower::Service;
+
+/// Provider used by every read, backed by one or more endpoints
+pub type ChainProvider = Provider<FailoverTransport>;
+
+/// JSON-RPC error codes used by providers to signal rate limiting
+const RATE_LIMIT_CODES: [i64; 2] = [429, -32005];
+
+/// How transient errors are retried
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// attempts after the first one, across all endpoints
+    pub max_retries: usize,
+    /// wait before the first retry, doubled on every attempt
+    pub initial_backoff: Duration,
+    /// print the endpoint that served each call to stderr
+    pub verbose: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(250),
+            verbose: false,
+        }
+    }
+}
+
+/// Non-success HTTP status of an endpoint, kept so retries are decided on the status code
+#[derive(Debug)]
+pub struct HttpStatusError {
+    pub status: StatusCode,
+    pub body: String,
+}
+
+impl std::fmt::Display for HttpStatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "HTTP {}: {}", self.status, self.body)
+    }
+}
+
+impl std::error::Error for HttpStatusError {}
+
+/// HTTP transport over several endpoints of the same chain.
+/// Transient errors (connection errors, rate limits, 5xx) are retried with backoff, rotating to the next endpoint
+#[derive(Debug, Clone)]
+pub struct FailoverTransport {
+    client: Client,
+    endpoints: Arc<Vec<(String, Url)>>,
+    current: Arc<AtomicUsize>,
+    served: Arc<AtomicUsize>,
+    policy: RetryPolicy,
+}
+
+impl FailoverTransport {
+    pub fn new(urls: &[String], policy: RetryPolicy) -> Result<Self> {
+        if urls.is_empty() {
+            return Err(eyre!("No RPC URL given"))
+        }
+        let mut endpoints = Vec::new();
+        for url in urls {
+            endpoints.push((url.clone(), url.parse()?));
+        }
+        Ok(Self {
+            client: Client::new(),
+            endpoints: Arc::new(endpoints),
+            current: Arc::new(AtomicUsize::new(0)),
+            served: Arc::new(AtomicUsize::new(0)),
+            policy,
+        })
+    }
+
+    /// URL of the endpoint that served the last call
+    pub fn last_endpoint(&self) -> &str {
+        &self.endpoints[self.served.load(Ordering::Relaxed)].0
+    }
+
+    fn rotate(&self, from: usize) {
+        let next = (from + 1) % self.endpoints.len();
+        let _ = self.current.compare_exchange(from, next, Ordering::Relaxed, Ordering::Relaxed);
+    }
+
+    async fn request(self, req: RequestPacket) -> Result<ResponsePacket, TransportError> {
+        let mut backoff = self.policy.initial_backoff;
+        let mut attempt = 0;
+        loop {
+            let idx = self.current.load(Ordering::Relaxed);
+            let (url, endpoint) = &self.endpoints[idx];
+            let result = self.send(endpoint, &req).await;
+            let transient = match &result {
+                Ok(response) => is_rate_limited(response),
+                Err(e) => is_transient(e),
+            };
+            if !transient || attempt >= self.policy.max_retries {
+                if result.is_ok() {
+                    self.served.store(idx, Ordering::Relaxed);
+                    if self.policy.verbose {
+                        eprintln!("[rpc] {} served by {}", method_names(&req), url);
+                    }
+                }
+                return result
+            }
+            if self.policy.verbose {
+                eprintln!("[rpc] {} failed on {}, retrying in {:?}", method_names(&req), url, backoff);
+            }
+            self.rotate(idx);
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+            attempt += 1;
+        }
+    }
+
+    async fn send(&self, endpoint: &Url, req: &RequestPacket) -> Result<ResponsePacket, TransportError> {
+        let body = serde_json::to_vec(req).map_err(TransportError::ser_err)?;
+        let response = self.client
+            .post(endpoint.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await
+            .map_err(TransportErrorKind::custom)?;
+        let status = response.status();
+        let body = response.text().await.map_err(TransportErrorKind::custom)?;
+        if !status.is_success() {
+            return Err(TransportErrorKind::custom(HttpStatusError { status, body }))
+        }
+        serde_json::from_str(&body).map_err(|e| TransportError::deser_err(e, &body))
+    }
+}
+
+impl Service<RequestPacket> for FailoverTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: RequestPacket) -> Self::Future {
+        Box::pin(self.clone().request(req))
+    }
+}
+
+fn method_names(req: &RequestPacket) -> String {
+    match req {
+        RequestPacket::Single(r) => r.method().to_string(),
+        RequestPacket::Batch(rs) => rs.iter().map(|r| r.method()).collect::<Vec<&str>>().join(","),
+    }
+}
+
+/// Connection errors, HTTP 429 and 5xx and rate limit error codes are worth retrying on another endpoint,
+/// reverts and other HTTP statuses are not
+fn is_transient(error: &TransportError) -> bool {
+    match error {
+        TransportError::Transport(TransportErrorKind::Custom(e)) => match e.downcast_ref::<HttpStatusError>() {
+            Some(e) => is_transient_status(e.status),
+            None => true,
+        },
+        TransportError::Transport(_) => true,
+        TransportError::ErrorResp(payload) => RATE_LIMIT_CODES.contains(&payload.code),
+        _ => false,
+    }
+}
+
+fn is_transient_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+fn is_rate_limited(response: &ResponsePacket) -> bool {
+    let limited = |payload: &ResponsePayload| match payload {
+        ResponsePayload::Failure(e) => RATE_LIMIT_CODES.contains(&e.code),
+        _ => false,
+    };
+    match response {
+        ResponsePacket::Single(r) => limited(&r.payload),
+        ResponsePacket::Batch(rs) => rs.iter().any(|r| limited(&r.payload)),
+    }
+}
+
+/// Builds a provider over `rpc_urls`, failing over between them
+pub fn get_provider(rpc_urls: &[String], policy: RetryPolicy) -> Result<Arc<ChainProvider>> {
+    let transport = FailoverTransport::new(rpc_urls, policy)?;
+    let is_local = rpc_urls.iter().all(|u| u.contains("localhost") || u.contains("127.0.0.1"));
+    let client = ClientBuilder::default().transport(transport, is_local);
+    let provider = Provider::new_with_client(client);
+    Ok(Arc::new(provider))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_json_rpc::ErrorPayload;
+
+    fn status_error(status: StatusCode) -> TransportError {
+        TransportErrorKind::custom(HttpStatusError { status, body: String::new() })
+    }
+
+    fn error_response(code: i64, message: &str) -> TransportError {
+        TransportError::ErrorResp(ErrorPayload { code, message: message.to_string(), data: None })
+    }
+
+    #[test]
+    fn transient_http_statuses() {
+        assert!(is_transient(&status_error(StatusCode::TOO_MANY_REQUESTS)));
+        assert!(is_transient(&status_error(StatusCode::INTERNAL_SERVER_ERROR)));
+        assert!(is_transient(&status_error(StatusCode::BAD_GATEWAY)));
+        assert!(is_transient(&status_error(StatusCode::SERVICE_UNAVAILABLE)));
+        assert!(!is_transient(&status_error(StatusCode::UNAUTHORIZED)));
+        assert!(!is_transient(&status_error(StatusCode::NOT_FOUND)));
+    }
+
+    #[test]
+    fn transient_error_codes() {
+        assert!(is_transient(&error_response(429, "Too Many Requests")));
+        assert!(is_transient(&error_response(-32005, "limit exceeded")));
+        // only the code counts, not the message
+        assert!(!is_transient(&error_response(3, "execution reverted: rate limit reached")));
+        assert!(!is_transient(&error_response(-32000, "header not found")));
+    }
+}
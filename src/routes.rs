@@ -0,0 +1,224 @@
+use alloy_primitives::{I256, U256};
+use datafeeds::OraclesIndex;
+use eyre::{eyre, Result};
+use serde::Serialize;
+use std::collections::{HashSet, VecDeque};
+use crate::constants::CROSS_RATE_INTERMEDIATES;
+use crate::output::Render;
+use crate::types::{Price, RoundData};
+
+/// Decimals of derived prices
+pub const DERIVED_DECIMALS: u8 = 18;
+
+/// A feed used in a route, `inverted` when the route goes from its quote to its base
+#[derive(Debug, Clone, Serialize)]
+pub struct Leg {
+    pub base: String,
+    pub quote: String,
+    pub inverted: bool,
+}
+
+impl Leg {
+    pub fn pair(&self) -> String {
+        format!("{}/{}", self.base, self.quote)
+    }
+}
+
+impl std::fmt::Display for Leg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.inverted {
+            write!(f, "1/({})", self.pair())
+        } else {
+            write!(f, "{}", self.pair())
+        }
+    }
+}
+
+/// Price composed from several feeds
+#[derive(Debug, Serialize)]
+pub struct DerivedPrice {
+    #[serde(flatten)]
+    pub price: Price,
+    pub route: String,
+    /// oldest update among the legs
+    pub oldest_updated_at: u64,
+    pub legs: Vec<RoundData>,
+}
+
+impl Render for DerivedPrice {
+    fn text(&self) -> String {
+        format!("{} in [{}] is {} via {} (oldest leg updated at {})",
+            self.price.pair, self.price.chain, self.price.price(), self.route, self.oldest_updated_at
+        )
+    }
+}
+
+/// Shortest route of at most `max_legs` feeds from `base` to `quote`,
+/// going through the assets in `CROSS_RATE_INTERMEDIATES`
+pub fn find_route(index: &OraclesIndex, base: &str, quote: &str, max_legs: usize) -> Option<Vec<Leg>> {
+    route_through(|b, q| index.get_oracle(b, q).is_some(), base, quote, max_legs)
+}
+
+/// Search of `find_route`, `has_feed(base, quote)` tells whether the index has a feed for the pair
+fn route_through(has_feed: impl Fn(&str, &str) -> bool, base: &str, quote: &str, max_legs: usize) -> Option<Vec<Leg>> {
+    let (base, quote) = (base.to_uppercase(), quote.to_uppercase());
+    let mut nodes = vec![base.clone(), quote.clone()];
+    for i in CROSS_RATE_INTERMEDIATES {
+        if !nodes.iter().any(|n| n == i) {
+            nodes.push(i.to_string());
+        }
+    }
+    let mut visited = HashSet::from([base.clone()]);
+    let mut queue = VecDeque::from([(base, Vec::<Leg>::new())]);
+    while let Some((node, path)) = queue.pop_front() {
+        if node == quote {
+            return Some(path)
+        }
+        if path.len() >= max_legs {
+            continue
+        }
+        for next in nodes.iter() {
+            if visited.contains(next) {
+                continue
+            }
+            let leg = if has_feed(&node, next) {
+                Leg { base: node.clone(), quote: next.clone(), inverted: false }
+            } else if has_feed(next, &node) {
+                Leg { base: next.clone(), quote: node.clone(), inverted: true }
+            } else {
+                continue
+            };
+            visited.insert(next.clone());
+            let mut path = path.clone();
+            path.push(leg);
+            queue.push_back((next.clone(), path));
+        }
+    }
+    None
+}
+
+/// Multiplies the legs of a route, `rounds` holds the latest round of each leg in the same order
+pub fn derive_price(chain: &str, base: &str, quote: &str, route: &[Leg], rounds: Vec<RoundData>, decimals: &[u8]) -> Result<DerivedPrice> {
+    let unit = |d: u8| U256::from(10u64).pow(U256::from(d));
+    let mut acc = unit(DERIVED_DECIMALS);
+    for ((leg, round), d) in route.iter().zip(rounds.iter()).zip(decimals) {
+        if round.answer <= I256::ZERO {
+            return Err(eyre!("Feed {} has a non positive answer", leg.pair()))
+        }
+        let answer = round.answer.into_raw();
+        acc = if leg.inverted {
+            acc * unit(*d) / answer
+        } else {
+            acc * answer / unit(*d)
+        };
+    }
+    let raw = I256::try_from(acc).map_err(|_| eyre!("Derived price of {}/{} overflows", base, quote))?;
+    Ok(DerivedPrice {
+        price: Price {
+            chain: chain.to_string(),
+            pair: format!("{}/{}", base.to_uppercase(), quote.to_uppercase()),
+            raw,
+            decimals: DERIVED_DECIMALS,
+        },
+        route: route.iter().map(|l| l.to_string()).collect::<Vec<String>>().join(" * "),
+        oldest_updated_at: rounds.iter().map(|r| r.updated_at).min().unwrap_or_default(),
+        legs: rounds,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(feeds: &[&str], base: &str, quote: &str, max_legs: usize) -> Option<Vec<String>> {
+        let has_feed = |b: &str, q: &str| feeds.contains(&format!("{}/{}", b, q).as_str());
+        route_through(has_feed, base, quote, max_legs).map(|legs| legs.iter().map(|l| l.to_string()).collect())
+    }
+
+    fn round(pair: &str, answer: u128, updated_at: u64) -> RoundData {
+        RoundData {
+            pair: pair.to_string(),
+            round_id: 1,
+            answer: I256::from_raw(U256::from(answer)),
+            price: String::new(),
+            started_at: updated_at,
+            updated_at,
+            answered_in_round: 1,
+        }
+    }
+
+    fn leg(base: &str, quote: &str, inverted: bool) -> Leg {
+        Leg { base: base.to_string(), quote: quote.to_string(), inverted }
+    }
+
+    const E8: u128 = 100_000_000;
+    const E18: u128 = 1_000_000_000_000_000_000;
+
+    #[test]
+    fn route_direct_pair() {
+        assert_eq!(route(&["ETH/USD"], "eth", "usd", 3), Some(vec!["ETH/USD".to_string()]));
+    }
+
+    #[test]
+    fn route_inverted_pair() {
+        assert_eq!(route(&["ETH/USD"], "USD", "ETH", 3), Some(vec!["1/(ETH/USD)".to_string()]));
+    }
+
+    #[test]
+    fn route_one_hop() {
+        let feeds = ["LINK/ETH", "ETH/USD", "EUR/USD"];
+        assert_eq!(route(&feeds, "LINK", "USD", 3), Some(vec!["LINK/ETH".to_string(), "ETH/USD".to_string()]));
+        assert_eq!(route(&feeds, "LINK", "EUR", 3), Some(vec![
+            "LINK/ETH".to_string(),
+            "ETH/USD".to_string(),
+            "1/(EUR/USD)".to_string(),
+        ]));
+    }
+
+    #[test]
+    fn route_not_found() {
+        let feeds = ["LINK/ETH", "ETH/USD"];
+        assert_eq!(route(&feeds, "LINK", "BTC", 3), None);
+        // too many legs
+        assert_eq!(route(&feeds, "LINK", "USD", 1), None);
+        // only the known intermediates are tried
+        assert_eq!(route(&["AAVE/LINK", "LINK/USD"], "AAVE", "USD", 3), None);
+    }
+
+    #[test]
+    fn derive_direct_pair() {
+        let price = derive_price("mainnet", "eth", "usd", &[leg("ETH", "USD", false)], vec![round("ETH/USD", 2000 * E8, 100)], &[8]).unwrap();
+        assert_eq!(price.price.pair, "ETH/USD");
+        assert_eq!(price.price.decimals, DERIVED_DECIMALS);
+        assert_eq!(price.price.raw, I256::from_raw(U256::from(2000 * E18)));
+        assert_eq!(price.route, "ETH/USD");
+    }
+
+    #[test]
+    fn derive_inverted_leg() {
+        let price = derive_price("mainnet", "USD", "ETH", &[leg("ETH", "USD", true)], vec![round("ETH/USD", 2000 * E8, 100)], &[8]).unwrap();
+        // 1 / 2000
+        assert_eq!(price.price.raw, I256::from_raw(U256::from(E18 / 2000)));
+        assert_eq!(price.route, "1/(ETH/USD)");
+    }
+
+    #[test]
+    fn derive_mixed_decimals() {
+        // LINK/ETH has 18 decimals, ETH/USD has 8
+        let legs = [leg("LINK", "ETH", false), leg("ETH", "USD", false)];
+        let rounds = vec![round("LINK/ETH", 5 * E18 / 1000, 200), round("ETH/USD", 2000 * E8, 100)];
+        let price = derive_price("mainnet", "LINK", "USD", &legs, rounds, &[18, 8]).unwrap();
+        assert_eq!(price.price.raw, I256::from_raw(U256::from(10 * E18)));
+        assert_eq!(price.route, "LINK/ETH * ETH/USD");
+        assert_eq!(price.oldest_updated_at, 100);
+        assert_eq!(price.legs.len(), 2);
+    }
+
+    #[test]
+    fn derive_non_positive_answer() {
+        let mut negative = round("ETH/USD", 0, 100);
+        negative.answer = I256::MINUS_ONE;
+        assert!(derive_price("mainnet", "ETH", "USD", &[leg("ETH", "USD", false)], vec![round("ETH/USD", 0, 100)], &[8]).is_err());
+        assert!(derive_price("mainnet", "ETH", "USD", &[leg("ETH", "USD", false)], vec![negative], &[8]).is_err());
+    }
+}
@@ -0,0 +1,202 @@
+use datafeeds::{Oracle, OraclesIndex};
+use serde::Serialize;
+use crate::output::Render;
+use crate::types::as_opt_string;
+use alloy_primitives::Address;
+
+/// Feed category given to feeds being shut down in the reference directory
+const DEPRECATING_CATEGORY: &str = "deprecating";
+
+/// Conditions on the feeds listed, unset fields match every feed
+#[derive(Debug, Clone, Default)]
+pub struct FeedFilter {
+    pub base: Option<String>,
+    pub quote: Option<String>,
+    pub category: Option<String>,
+    pub feed_type: Option<String>,
+    pub decimals: Option<u8>,
+    /// only deprecated feeds with `Some(true)`, only live ones with `Some(false)`
+    pub deprecated: Option<bool>,
+}
+
+/// Feed of the reference index
+#[derive(Debug, Serialize)]
+pub struct FeedEntry {
+    pub chain: String,
+    pub name: String,
+    pub base: Option<String>,
+    pub quote: Option<String>,
+    #[serde(serialize_with = "as_opt_string")]
+    pub proxy_address: Option<Address>,
+    pub decimals: Option<u8>,
+    pub category: Option<String>,
+    pub feed_type: Option<String>,
+    pub deprecated: bool,
+}
+
+impl FeedEntry {
+    pub fn new(chain: &str, oracle: &Oracle) -> Self {
+        let name = oracle.name.clone().unwrap_or_default();
+        let (base, quote) = split_name(&name);
+        Self {
+            chain: chain.to_string(),
+            base,
+            quote,
+            name,
+            proxy_address: oracle.proxy_address,
+            decimals: oracle.decimals,
+            category: oracle.feed_category.clone(),
+            feed_type: oracle.feed_type.clone(),
+            deprecated: oracle
+                .feed_category
+                .as_deref()
+                .map_or(false, |c| c.eq_ignore_ascii_case(DEPRECATING_CATEGORY)),
+        }
+    }
+}
+
+impl Render for FeedEntry {
+    fn text(&self) -> String {
+        format!("[{}] {} {} (decimals {}, {}{})",
+            self.chain,
+            self.name,
+            self.proxy_address.map_or("no proxy".to_string(), |p| p.to_string()),
+            self.decimals.map_or("unknown".to_string(), |d| d.to_string()),
+            self.category.as_deref().unwrap_or("uncategorized"),
+            if self.deprecated { ", deprecated" } else { "" },
+        )
+    }
+}
+
+impl FeedFilter {
+    pub fn matches(&self, entry: &FeedEntry) -> bool {
+        let same = |wanted: &Option<String>, value: &Option<String>| match wanted {
+            None => true,
+            Some(w) => value.as_deref().map_or(false, |v| v.eq_ignore_ascii_case(w)),
+        };
+        same(&self.base, &entry.base)
+            && same(&self.quote, &entry.quote)
+            && same(&self.category, &entry.category)
+            && same(&self.feed_type, &entry.feed_type)
+            && self.decimals.map_or(true, |d| entry.decimals == Some(d))
+            && self.deprecated.map_or(true, |d| entry.deprecated == d)
+    }
+}
+
+/// Feeds of the index matching the filter, sorted by name
+pub fn list_feeds(chain: &str, index: &OraclesIndex, filter: &FeedFilter) -> Vec<FeedEntry> {
+    let mut entries: Vec<FeedEntry> = index
+        .oracles
+        .iter()
+        .map(|o| FeedEntry::new(chain, o))
+        .filter(|e| filter.matches(e))
+        .collect();
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    entries
+}
+
+/// Feeds of the index matching the filter whose name matches `query`, best matches first.
+/// Every word of the query must appear in the name, letters in order but not necessarily together
+/// (`eth usd` finds `ETH / USD`, `wsteth` finds `WSTETH / ETH Exchange Rate`)
+pub fn search_feeds(chain: &str, index: &OraclesIndex, filter: &FeedFilter, query: &str) -> Vec<FeedEntry> {
+    rank_by_query(list_feeds(chain, index, filter), query)
+}
+
+/// Entries whose name matches every word of `query`, by decreasing score then shorter names first
+fn rank_by_query(entries: Vec<FeedEntry>, query: &str) -> Vec<FeedEntry> {
+    let words: Vec<String> = query.split_whitespace().map(normalize).filter(|w| !w.is_empty()).collect();
+    let mut scored: Vec<(usize, FeedEntry)> = entries
+        .into_iter()
+        .filter_map(|e| {
+            let mut score = 0;
+            for word in words.iter() {
+                score += match_score(&e.name, word)?;
+            }
+            Some((score, e))
+        })
+        .collect();
+    scored.sort_by(|(a, ea), (b, eb)| b.cmp(a).then(ea.name.len().cmp(&eb.name.len())));
+    scored.into_iter().map(|(_, e)| e).collect()
+}
+
+/// Base and quote of a feed name like `ETH / USD`
+fn split_name(name: &str) -> (Option<String>, Option<String>) {
+    match name.split_once('/') {
+        Some((base, quote)) => {
+            let first_word = |s: &str| s.split_whitespace().next().map(|w| w.to_uppercase());
+            (first_word(base), first_word(quote))
+        },
+        None => (None, None),
+    }
+}
+
+fn normalize(input: &str) -> String {
+    input.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+/// Score of a (normalized) query word against a feed name: 3 when it is a whole word of the name
+/// (`exchange` in `WSTETH / ETH Exchange Rate`), 2 when it is part of one, 1 when its letters appear in order.
+/// `None` when they do not
+fn match_score(name: &str, word: &str) -> Option<usize> {
+    if name.split(|c: char| !c.is_alphanumeric()).any(|token| normalize(token) == word) {
+        return Some(3)
+    }
+    let name = normalize(name);
+    if name.contains(word) {
+        return Some(2)
+    }
+    let mut chars = name.chars();
+    word.chars().all(|w| chars.any(|c| c == w)).then_some(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str) -> FeedEntry {
+        FeedEntry {
+            chain: "mainnet".to_string(),
+            name: name.to_string(),
+            base: None,
+            quote: None,
+            proxy_address: None,
+            decimals: None,
+            category: None,
+            feed_type: None,
+            deprecated: false,
+        }
+    }
+
+    fn ranked(names: &[&str], query: &str) -> Vec<String> {
+        rank_by_query(names.iter().map(|n| entry(n)).collect(), query)
+            .into_iter()
+            .map(|e| e.name)
+            .collect()
+    }
+
+    #[test]
+    fn score_whole_words() {
+        assert_eq!(match_score("ETH / USD", "eth"), Some(3));
+        assert_eq!(match_score("WSTETH / ETH Exchange Rate", "exchange"), Some(3));
+        assert_eq!(match_score("WSTETH / ETH Exchange Rate", "wsteth"), Some(3));
+        assert_eq!(match_score("WSTETH / ETH Exchange Rate", "eth"), Some(3));
+    }
+
+    #[test]
+    fn score_partial_matches() {
+        assert_eq!(match_score("STETH / USD", "eth"), Some(2));
+        assert_eq!(match_score("WSTETH / ETH Exchange Rate", "exchangerate"), Some(2));
+        assert_eq!(match_score("WSTETH / ETH Exchange Rate", "wstrate"), Some(1));
+        assert_eq!(match_score("EUR / USD", "eth"), None);
+    }
+
+    #[test]
+    fn ranking_order() {
+        let names = ["STETH / USD", "EUR / USD", "ETH / USDT", "ETH / USD", "WSTETH / ETH Exchange Rate"];
+        // whole words first, ties broken by the shorter name
+        assert_eq!(ranked(&names, "eth usd"), vec!["ETH / USD", "ETH / USDT", "STETH / USD"]);
+        assert_eq!(ranked(&names, "eth"), vec!["ETH / USD", "ETH / USDT", "WSTETH / ETH Exchange Rate", "STETH / USD"]);
+        assert_eq!(ranked(&names, "exchange rate"), vec!["WSTETH / ETH Exchange Rate"]);
+        assert!(ranked(&names, "btc").is_empty());
+    }
+}
@@ -0,0 +1,71 @@
+use alloy_primitives::{Address, I256};
+use serde::Serialize;
+use crate::functions::datafeeds::GetRoundDataReturn;
+use crate::output::Render;
+use crate::types::{as_string, RoundData};
+
+/// State of an L2 sequencer read from its uptime feed
+#[derive(Debug, Serialize)]
+pub struct SequencerStatus {
+    pub chain: String,
+    #[serde(serialize_with = "as_string")]
+    pub feed: Address,
+    pub up: bool,
+    /// time of the last status change (restart when up)
+    pub since: u64,
+    /// seconds since the last status change
+    pub elapsed: u64,
+    pub grace_period: u64,
+    /// prices can be trusted: the sequencer is up and the grace period is over
+    pub trusted: bool,
+}
+
+impl SequencerStatus {
+    pub fn new(chain: &str, feed: Address, round: &GetRoundDataReturn, now: u64, grace_period: u64) -> Self {
+        let up = round.answer == I256::ZERO;
+        let since = round.startedAt.to::<u64>();
+        let elapsed = now.saturating_sub(since);
+        Self {
+            chain: chain.to_string(),
+            feed,
+            up,
+            since,
+            elapsed,
+            grace_period,
+            trusted: up && elapsed > grace_period,
+        }
+    }
+}
+
+impl Render for SequencerStatus {
+    fn text(&self) -> String {
+        let state = match (self.up, self.trusted) {
+            (true, true) => "up",
+            (true, false) => "up, within grace period",
+            (false, _) => "DOWN",
+        };
+        format!("sequencer of [{}] is {} since {} ({}s ago, grace period {}s)",
+            self.chain, state, self.since, self.elapsed, self.grace_period
+        )
+    }
+}
+
+/// Latest round of a feed with the status of the sequencer of its chain (`None` on chains without sequencer)
+#[derive(Debug, Serialize)]
+pub struct SequencedRound {
+    #[serde(flatten)]
+    pub round: RoundData,
+    pub sequencer: Option<SequencerStatus>,
+    /// why the sequencer status is unknown
+    pub sequencer_error: Option<String>,
+}
+
+impl Render for SequencedRound {
+    fn text(&self) -> String {
+        match (&self.sequencer, &self.sequencer_error) {
+            (Some(sequencer), _) => format!("{}\n{}", self.round.text(), sequencer.text()),
+            (None, Some(e)) => format!("{}\nsequencer status UNKNOWN: {}", self.round.text(), e),
+            (None, None) => self.round.text(),
+        }
+    }
+}
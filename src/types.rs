@@ -0,0 +1,570 @@
+use alloy_primitives::{utils::format_units, Address, B256, I256, U256};
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+use std::fmt::Display;
+use crate::functions::datafeeds::{GetRoundDataReturn, Phase};
+use crate::output::Render;
+
+/// Serializes big numbers and addresses as strings, so they survive JSON parsers and spreadsheets
+pub fn as_string<T: Display, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+pub fn as_opt_string<T: Display, S: Serializer>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(v) => serializer.collect_str(v),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeedInfo {
+    pub chain: String,
+    pub pair: String,
+    pub name: Option<String>,
+    #[serde(serialize_with = "as_opt_string")]
+    pub proxy_address: Option<Address>,
+    pub decimals: Option<u8>,
+}
+
+impl Render for FeedInfo {
+    fn text(&self) -> String {
+        format!("{:#?}", self)
+    }
+}
+
+/// Answer of a feed with the decimals needed to read it, answers are signed
+#[derive(Debug, Clone)]
+pub struct Price {
+    pub chain: String,
+    pub pair: String,
+    pub raw: I256,
+    pub decimals: u8,
+}
+
+impl Price {
+    /// Answer scaled by the feed decimals, with its sign (`-0.0125`)
+    pub fn price(&self) -> String {
+        format_units(self.raw, self.decimals).unwrap_or_default()
+    }
+}
+
+impl std::fmt::Display for Price {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.price())
+    }
+}
+
+impl Serialize for Price {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Price", 5)?;
+        state.serialize_field("chain", &self.chain)?;
+        state.serialize_field("pair", &self.pair)?;
+        state.serialize_field("raw", &self.raw.to_string())?;
+        state.serialize_field("decimals", &self.decimals)?;
+        state.serialize_field("price", &self.price())?;
+        state.end()
+    }
+}
+
+impl Render for Price {
+    fn text(&self) -> String {
+        format!("{} in [{}] is {} [{}]", self.pair, self.chain, self.raw, self.price())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnswerStatus {
+    Ok,
+    /// no feed for the pair in the reference index
+    NoFeed,
+    /// feed exists but its answer could not be read
+    CallFailed,
+    /// no feed for the pair, price composed from other feeds
+    Derived,
+}
+
+/// Latest answer of one of several requested pairs
+#[derive(Debug)]
+pub struct PairAnswer {
+    pub chain: String,
+    pub pair: String,
+    pub status: AnswerStatus,
+    pub price: Option<Price>,
+    /// feeds composed for a derived price
+    pub route: Option<String>,
+    /// oldest update among the feeds of a derived price
+    pub oldest_updated_at: Option<u64>,
+}
+
+/// Same fields for every pair, so table and csv rows stay aligned
+impl Serialize for PairAnswer {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("PairAnswer", 8)?;
+        state.serialize_field("chain", &self.chain)?;
+        state.serialize_field("pair", &self.pair)?;
+        state.serialize_field("status", &self.status)?;
+        state.serialize_field("raw", &self.price.as_ref().map(|p| p.raw.to_string()))?;
+        state.serialize_field("decimals", &self.price.as_ref().map(|p| p.decimals))?;
+        state.serialize_field("price", &self.price.as_ref().map(|p| p.price()))?;
+        state.serialize_field("route", &self.route)?;
+        state.serialize_field("oldest_updated_at", &self.oldest_updated_at)?;
+        state.end()
+    }
+}
+
+impl Render for PairAnswer {
+    fn text(&self) -> String {
+        match (&self.price, self.status) {
+            (Some(price), AnswerStatus::Derived) => format!("{} (derived via {}, oldest leg updated at {})",
+                price.text(),
+                self.route.as_deref().unwrap_or_default(),
+                self.oldest_updated_at.map_or("unknown".to_string(), |t| t.to_string()),
+            ),
+            (Some(price), _) => price.text(),
+            (None, AnswerStatus::NoFeed) => format!("{} in [{}]: no feed", self.pair, self.chain),
+            (None, _) => format!("{} in [{}]: error getting latest answer", self.pair, self.chain),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RoundData {
+    pub pair: String,
+    #[serde(serialize_with = "as_string")]
+    pub round_id: u128,
+    #[serde(serialize_with = "as_string")]
+    pub answer: I256,
+    pub price: String,
+    pub started_at: u64,
+    pub updated_at: u64,
+    #[serde(serialize_with = "as_string")]
+    pub answered_in_round: u128,
+}
+
+impl RoundData {
+    pub fn new(pair: &str, round: &GetRoundDataReturn, decimals: u8) -> Self {
+        Self {
+            pair: pair.to_string(),
+            round_id: round.roundId,
+            answer: round.answer,
+            price: format_units(round.answer, decimals).unwrap_or_default(),
+            started_at: round.startedAt.to::<u64>(),
+            updated_at: round.updatedAt.to::<u64>(),
+            answered_in_round: round.answeredInRound,
+        }
+    }
+}
+
+impl Render for RoundData {
+    fn text(&self) -> String {
+        format!("{}\nroundId: {}\nanswer: {} [{}]\nstarted at: {}\nupdated at: {}\nanswered in round: {}",
+            self.pair,
+            self.round_id,
+            self.answer,
+            self.price,
+            self.started_at,
+            self.updated_at,
+            self.answered_in_round
+        )
+    }
+}
+
+/// Round that was the latest one at a block
+#[derive(Debug, Serialize)]
+pub struct RoundAtBlock {
+    pub block: u64,
+    #[serde(flatten)]
+    pub round: RoundData,
+}
+
+impl Render for RoundAtBlock {
+    fn text(&self) -> String {
+        format!("at block {}: {}", self.block, self.round.text())
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Description {
+    #[serde(serialize_with = "as_string")]
+    pub proxy_address: Address,
+    pub description: String,
+}
+
+impl Render for Description {
+    fn text(&self) -> String {
+        format!("Description for {} is: {}", self.proxy_address, self.description)
+    }
+}
+
+impl Render for Phase {
+    fn text(&self) -> String {
+        let last_round = self.last_round.map_or("unknown".to_string(), |r| r.to_string());
+        format!("phase {}: aggregator {} (version {}) rounds {} to {}",
+            self.id, self.aggregator, self.version, self.first_round, last_round
+        )
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RouterInfo {
+    pub chain: String,
+    pub router: String,
+}
+
+impl Render for RouterInfo {
+    fn text(&self) -> String {
+        format!("Router for {} is {}", self.chain, self.router)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SelectorInfo {
+    pub chain: String,
+    pub selector: String,
+}
+
+impl Render for SelectorInfo {
+    fn text(&self) -> String {
+        format!("Selector for {} is {}", self.chain, self.selector)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LaneInfo {
+    pub origin: String,
+    pub destination: String,
+    #[serde(serialize_with = "as_string")]
+    pub on_ramp: Address,
+    #[serde(serialize_with = "as_string")]
+    pub off_ramp: Address,
+    #[serde(serialize_with = "as_string")]
+    pub commit_store: Address,
+}
+
+impl Render for LaneInfo {
+    fn text(&self) -> String {
+        format!("Lane {} -> {}\non ramp: {}\noff ramp: {}\ncommit store: {}",
+            self.origin, self.destination, self.on_ramp, self.off_ramp, self.commit_store
+        )
+    }
+}
+
+/// Fee to send a CCIP message, in one fee token
+#[derive(Debug, Serialize)]
+pub struct CcipFee {
+    pub origin: String,
+    pub destination: String,
+    /// `native`, `LINK` or the address of the fee token
+    pub fee_token: String,
+    #[serde(serialize_with = "as_string")]
+    pub raw: U256,
+    /// fee with 18 decimals, only for native and LINK
+    pub fee: Option<String>,
+}
+
+impl Render for CcipFee {
+    fn text(&self) -> String {
+        format!("Fee {} -> {} in {}: {}",
+            self.origin, self.destination, self.fee_token, self.fee.clone().unwrap_or(self.raw.to_string())
+        )
+    }
+}
+
+/// Token bucket of a CCIP rate limiter
+#[derive(Debug, Clone, Serialize)]
+pub struct RateLimiterState {
+    /// tokens available at `last_updated`
+    #[serde(serialize_with = "as_string")]
+    pub tokens: u128,
+    pub last_updated: u64,
+    pub is_enabled: bool,
+    #[serde(serialize_with = "as_string")]
+    pub capacity: u128,
+    /// tokens added per second
+    #[serde(serialize_with = "as_string")]
+    pub rate: u128,
+}
+
+impl RateLimiterState {
+    /// Tokens that can go through now, everything when the limiter is disabled
+    pub fn available(&self) -> Option<u128> {
+        self.is_enabled.then_some(self.tokens.min(self.capacity))
+    }
+
+    /// Seconds until the bucket is full again
+    pub fn seconds_to_full(&self) -> Option<u64> {
+        self.seconds_until(self.capacity)
+    }
+
+    /// Seconds until `amount` is available, `None` when it never will (above capacity or no refill)
+    pub fn seconds_until(&self, amount: u128) -> Option<u64> {
+        if !self.is_enabled || amount <= self.tokens {
+            return Some(0)
+        }
+        if amount > self.capacity || self.rate == 0 {
+            return None
+        }
+        let missing = amount - self.tokens;
+        Some(missing.div_ceil(self.rate) as u64)
+    }
+}
+
+impl Render for RateLimiterState {
+    fn text(&self) -> String {
+        if !self.is_enabled {
+            return "rate limiter disabled".to_string()
+        }
+        format!("{} of {} available (refills {}/s, updated at {})", self.tokens, self.capacity, self.rate, self.last_updated)
+    }
+}
+
+/// Token that can be transferred through a CCIP lane
+#[derive(Debug, Serialize)]
+pub struct LaneToken {
+    #[serde(serialize_with = "as_string")]
+    pub token: Address,
+    pub symbol: Option<String>,
+    pub decimals: Option<u8>,
+    #[serde(serialize_with = "as_opt_string")]
+    pub pool: Option<Address>,
+    /// bucket of the pool for the onRamp of the lane
+    pub rate_limiter: Option<RateLimiterState>,
+}
+
+impl Render for LaneToken {
+    fn text(&self) -> String {
+        format!("{} {} pool {}: {}",
+            self.symbol.as_deref().unwrap_or("unknown"),
+            self.token,
+            self.pool.map_or("unknown".to_string(), |p| p.to_string()),
+            self.rate_limiter.as_ref().map_or("rate limiter unknown".to_string(), |r| r.text()),
+        )
+    }
+}
+
+/// Fee tokens and transferable tokens of a CCIP lane
+#[derive(Debug, Serialize)]
+pub struct LaneTokens {
+    pub origin: String,
+    pub destination: String,
+    #[serde(serialize_with = "as_string")]
+    pub on_ramp: Address,
+    pub fee_tokens: Vec<String>,
+    pub tokens: Vec<LaneToken>,
+}
+
+impl Render for LaneTokens {
+    fn text(&self) -> String {
+        let mut lines = vec![
+            format!("Lane {} -> {} (on ramp {})", self.origin, self.destination, self.on_ramp),
+            format!("fee tokens: {}", self.fee_tokens.join(", ")),
+            format!("{} transferable tokens:", self.tokens.len()),
+        ];
+        for token in self.tokens.iter() {
+            lines.push(format!("  {}", token.text()));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Progress of a CCIP message from the source to the destination chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageState {
+    /// sent on the source, not committed yet
+    Sent,
+    /// committed on the destination, waiting for execution
+    Committed,
+    InProgress,
+    Executed,
+    Failed,
+}
+
+impl MessageState {
+    /// No further change is expected
+    pub fn is_final(&self) -> bool {
+        matches!(self, MessageState::Executed | MessageState::Failed)
+    }
+}
+
+impl std::fmt::Display for MessageState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MessageState::Sent => write!(f, "sent"),
+            MessageState::Committed => write!(f, "committed"),
+            MessageState::InProgress => write!(f, "in progress"),
+            MessageState::Executed => write!(f, "executed"),
+            MessageState::Failed => write!(f, "FAILED"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CcipMessageStatus {
+    #[serde(serialize_with = "as_string")]
+    pub message_id: B256,
+    pub sequence_number: u64,
+    #[serde(serialize_with = "as_opt_string")]
+    pub source_tx: Option<B256>,
+    pub state: MessageState,
+    #[serde(serialize_with = "as_opt_string")]
+    pub destination_tx: Option<B256>,
+    /// destination blocks searched without finding the transaction that set the state
+    pub searched_blocks: Option<BlockRange>,
+}
+
+/// Inclusive range of blocks
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct BlockRange {
+    pub from: u64,
+    pub to: u64,
+}
+
+impl Render for CcipMessageStatus {
+    fn text(&self) -> String {
+        let mut line = format!("message {} (sequence {}) is {}", self.message_id, self.sequence_number, self.state);
+        if let Some(tx) = self.source_tx {
+            line.push_str(&format!("\nsource tx: {}", tx));
+        }
+        if let Some(tx) = self.destination_tx {
+            line.push_str(&format!("\ndestination tx: {}", tx));
+        }
+        if let Some(range) = self.searched_blocks {
+            line.push_str(&format!("\ndestination tx: not found in blocks {} to {}", range.from, range.to));
+        }
+        line
+    }
+}
+
+/// Transaction sent (or simulated) on the source chain for a CCIP transfer
+#[derive(Debug, Serialize)]
+pub struct CcipTransaction {
+    /// `approve` of a token or `ccipSend`
+    pub action: String,
+    #[serde(serialize_with = "as_string")]
+    pub to: Address,
+    #[serde(serialize_with = "as_string")]
+    pub value: U256,
+    pub calldata: String,
+    #[serde(serialize_with = "as_opt_string")]
+    pub tx_hash: Option<B256>,
+}
+
+/// Result of `ccip-send`, nothing is sent in a dry run
+#[derive(Debug, Serialize)]
+pub struct CcipSend {
+    pub origin: String,
+    pub destination: String,
+    #[serde(serialize_with = "as_string")]
+    pub from: Address,
+    pub dry_run: bool,
+    /// `native` or the address of the fee token
+    pub fee_token: String,
+    #[serde(serialize_with = "as_string")]
+    pub fee: U256,
+    #[serde(serialize_with = "as_opt_string")]
+    pub message_id: Option<B256>,
+    /// why a dry run gives no message id: simulation skipped or failed
+    pub simulation: Option<String>,
+    pub transactions: Vec<CcipTransaction>,
+}
+
+impl Render for CcipSend {
+    fn text(&self) -> String {
+        let mut lines = vec![format!("{} {} -> {} from {}, fee {} in {}",
+            if self.dry_run { "Simulated message" } else { "Message" },
+            self.origin, self.destination, self.from, self.fee, self.fee_token
+        )];
+        for tx in self.transactions.iter() {
+            lines.push(format!("{} on {} (value {}){}\n  calldata: {}",
+                tx.action,
+                tx.to,
+                tx.value,
+                tx.tx_hash.map_or(String::new(), |h| format!(" tx {}", h)),
+                tx.calldata,
+            ));
+        }
+        lines.push(format!("message id: {}", self.message_id.map_or("unknown".to_string(), |id| id.to_string())));
+        if let Some(simulation) = self.simulation.as_ref() {
+            lines.push(simulation.clone());
+        }
+        lines.join("\n")
+    }
+}
+
+/// Rate limiter on the path of a CCIP lane
+#[derive(Debug, Serialize)]
+pub struct RateLimit {
+    /// `on_ramp`, `off_ramp` (aggregate, USD with 18 decimals) or the source or destination pool of a token
+    pub scope: String,
+    #[serde(serialize_with = "as_opt_string")]
+    pub token: Option<Address>,
+    pub symbol: Option<String>,
+    pub state: Option<RateLimiterState>,
+    /// amount that can go through now, unlimited when the limiter is disabled
+    #[serde(serialize_with = "as_opt_string")]
+    pub available: Option<u128>,
+    /// seconds until the bucket is full
+    pub full_in: Option<u64>,
+    /// transfer checked against the limiter, its USD value (18 decimals) for the aggregate limiters
+    #[serde(serialize_with = "as_opt_string")]
+    pub amount: Option<u128>,
+    /// seconds until the amount can go through, `None` when it never will
+    pub amount_in: Option<u64>,
+}
+
+impl Render for RateLimit {
+    fn text(&self) -> String {
+        let name = match (&self.symbol, self.token) {
+            (Some(symbol), _) => format!("{} {}", self.scope, symbol),
+            (None, Some(token)) => format!("{} {}", self.scope, token),
+            (None, None) => self.scope.clone(),
+        };
+        let Some(state) = &self.state else {
+            return format!("{}: rate limiter unknown", name)
+        };
+        if !state.is_enabled {
+            return format!("{}: no limit", name)
+        }
+        // aggregate limiters count USD with 18 decimals, pools count the smallest unit of the token
+        let value = |v: u128| match self.token {
+            None => format!("${}", format_units(U256::from(v), 18).unwrap_or_default()),
+            Some(_) => v.to_string(),
+        };
+        let mut line = format!("{}: {} available of {}, refills {}/s, full in {}s",
+            name,
+            value(self.available.unwrap_or_default()),
+            value(state.capacity),
+            value(state.rate),
+            self.full_in.map_or("never".to_string(), |s| s.to_string()),
+        );
+        if let Some(amount) = self.amount {
+            match self.amount_in {
+                Some(0) => line.push_str(&format!(", {} can go through now", value(amount))),
+                Some(s) => line.push_str(&format!(", {} can go through in {}s", value(amount), s)),
+                None => line.push_str(&format!(", {} is above what the limiter can release", value(amount))),
+            }
+        }
+        line
+    }
+}
+
+impl RateLimit {
+    /// Row for a limiter, `amount` is the transfer checked against it
+    pub fn new(scope: &str, token: Option<Address>, symbol: Option<String>, state: Option<RateLimiterState>, amount: Option<u128>) -> Self {
+        Self {
+            scope: scope.to_string(),
+            token,
+            symbol,
+            available: state.as_ref().and_then(|s| s.available()),
+            full_in: state.as_ref().and_then(|s| s.seconds_to_full()),
+            amount,
+            amount_in: match (&state, amount) {
+                (Some(s), Some(a)) => s.seconds_until(a),
+                _ => None,
+            },
+            state,
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use alloy_primitives::Address;
+use datafeeds::Oracle;
+use serde::Serialize;
+use crate::functions::datafeeds::ProxyMetadata;
+use crate::output::Render;
+use crate::types::as_opt_string;
+
+/// Differences between the reference index and what the proxy reports on chain
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum IndexIssue {
+    /// the index has no proxy address for the feed
+    NoProxy,
+    /// no call answered: nothing deployed at the address or not a feed proxy
+    DeadAddress,
+    /// the proxy points to no aggregator
+    NoAggregator,
+    DecimalsMismatch { index: Option<u8>, onchain: Option<u8> },
+    /// the description of the proxy is not the name of the feed
+    DescriptionMismatch { name: String, description: Option<String> },
+}
+
+impl std::fmt::Display for IndexIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let or_unknown = |v: Option<u8>| v.map_or("unknown".to_string(), |v| v.to_string());
+        match self {
+            IndexIssue::NoProxy => write!(f, "no proxy address in the index"),
+            IndexIssue::DeadAddress => write!(f, "address does not answer as a feed proxy"),
+            IndexIssue::NoAggregator => write!(f, "proxy has no aggregator"),
+            IndexIssue::DecimalsMismatch { index, onchain } => write!(f, "decimals are {} in the index but {} on chain", or_unknown(*index), or_unknown(*onchain)),
+            IndexIssue::DescriptionMismatch { name, description } => write!(f, "description {:?} does not match {:?}", description.as_deref().unwrap_or_default(), name),
+        }
+    }
+}
+
+/// Result of checking a feed of the index against its proxy
+#[derive(Debug, Serialize)]
+pub struct FeedCheck {
+    pub chain: String,
+    pub name: String,
+    #[serde(serialize_with = "as_opt_string")]
+    pub proxy_address: Option<Address>,
+    #[serde(serialize_with = "as_opt_string")]
+    pub aggregator: Option<Address>,
+    pub valid: bool,
+    pub issues: Vec<IndexIssue>,
+}
+
+impl Render for FeedCheck {
+    fn text(&self) -> String {
+        let proxy = self.proxy_address.map_or("no proxy".to_string(), |p| p.to_string());
+        if self.valid {
+            return format!("[{}] {} {}: ok", self.chain, self.name, proxy)
+        }
+        let mut lines = vec![format!("[{}] {} {}: INVALID", self.chain, self.name, proxy)];
+        for issue in self.issues.iter() {
+            lines.push(format!("  - {}", issue));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Compares the index entry of a feed with the metadata read on its proxy (`None` when it has no proxy)
+pub fn check_oracle(chain: &str, oracle: &Oracle, metadata: Option<&ProxyMetadata>) -> FeedCheck {
+    let name = oracle.name.clone().unwrap_or_default();
+    let mut issues = Vec::new();
+    match metadata {
+        None => issues.push(IndexIssue::NoProxy),
+        Some(m) if m.description.is_none() && m.decimals.is_none() && m.aggregator.is_none() => {
+            issues.push(IndexIssue::DeadAddress)
+        },
+        Some(m) => {
+            if m.aggregator.map_or(true, |a| a == Address::ZERO) {
+                issues.push(IndexIssue::NoAggregator);
+            }
+            if oracle.decimals != m.decimals {
+                issues.push(IndexIssue::DecimalsMismatch { index: oracle.decimals, onchain: m.decimals });
+            }
+            if !same_name(&name, m.description.as_deref().unwrap_or_default()) {
+                issues.push(IndexIssue::DescriptionMismatch { name: name.clone(), description: m.description.clone() });
+            }
+        },
+    }
+    FeedCheck {
+        chain: chain.to_string(),
+        name,
+        proxy_address: oracle.proxy_address,
+        aggregator: metadata.and_then(|m| m.aggregator),
+        valid: issues.is_empty(),
+        issues,
+    }
+}
+
+/// Names compared ignoring case, spaces and punctuation (`ETH / USD` is `ETH/USD`).
+/// Pairs compare the base and the whole quote, so `ETH / USDT` is not `ETH / USD`
+/// and `BTC / USD Total Reserve` is not `BTC / USD`
+fn same_name(name: &str, description: &str) -> bool {
+    let normalize = |s: &str| s.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect::<String>();
+    let pair = |s: &str| s.split_once('/').map(|(base, quote)| (normalize(base), normalize(quote)));
+    match (pair(name), pair(description)) {
+        (Some(name), Some(description)) => !description.0.is_empty() && name == description,
+        _ => !normalize(description).is_empty() && normalize(name) == normalize(description),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_pair_names() {
+        assert!(same_name("ETH / USD", "ETH / USD"));
+        assert!(same_name("ETH / USD", "eth/usd"));
+        assert!(same_name("WSTETH / ETH Exchange Rate", "wstETH / ETH Exchange Rate"));
+        assert!(same_name("USDC / USD", "USDC / USD "));
+    }
+
+    #[test]
+    fn different_pair_names() {
+        assert!(!same_name("ETH / USD", "ETH / USDT"));
+        assert!(!same_name("ETH / USDT", "ETH / USD"));
+        assert!(!same_name("BTC / USD Total Reserve", "BTC / USD"));
+        assert!(!same_name("WSTETH / ETH Exchange Rate", "wstETH / ETH"));
+        assert!(!same_name("STETH / ETH", "ETH / STETH"));
+        // same letters, different split
+        assert!(!same_name("ETH / USD", "ET / HUSD"));
+        assert!(!same_name("ETH / USD", ""));
+    }
+
+    #[test]
+    fn non_pair_names() {
+        assert!(same_name("Total Marketcap USD", "Total Marketcap USD"));
+        assert!(same_name("Total Marketcap USD", "total-marketcap-usd"));
+        assert!(!same_name("Total Marketcap USD", "Total Marketcap"));
+        // a pair against a description without separator falls back to the whole name
+        assert!(same_name("ETH / USD", "ETHUSD"));
+        assert!(!same_name("", ""));
+    }
+}
@@ -0,0 +1,126 @@
+use alloy_primitives::{keccak256, Address, Bytes, B256, U256};
+use alloy_providers::provider::TempProvider;
+use alloy_rlp::{BufMut, Encodable, Header};
+use alloy_rpc_types::{CallInput, CallRequest, TransactionReceipt};
+use alloy_signer::{LocalWallet, Signer};
+use eyre::{eyre, Result};
+use std::{sync::Arc, time::Duration};
+use crate::provider::ChainProvider;
+
+/// Environment variable holding the key used to sign transactions
+pub const PRIVATE_KEY_ENV: &str = "PRIVATE_KEY";
+
+/// Extra gas over the estimate, in percent
+const GAS_MARGIN_PCT: u64 = 20;
+
+/// Seconds between polls of a transaction receipt
+const RECEIPT_POLL_INTERVAL: u64 = 2;
+
+/// Receipt polls before giving up on a transaction (10 minutes)
+const RECEIPT_MAX_POLLS: u64 = 300;
+
+/// Wallet from the hex private key in `PRIVATE_KEY` (read from `.env` too)
+pub fn wallet_from_env() -> Result<LocalWallet> {
+    let key = std::env::var(PRIVATE_KEY_ENV).map_err(|_| eyre!("No private key supplied in {PRIVATE_KEY_ENV}"))?;
+    key.trim().parse::<LocalWallet>().map_err(|e| eyre!("Invalid private key in {PRIVATE_KEY_ENV}: {}", e))
+}
+
+/// Call from `from`, as sent by `send_transaction`
+pub fn call_request(from: Address, to: Address, value: U256, data: Bytes) -> CallRequest {
+    CallRequest {
+        from: Some(from),
+        to: Some(to),
+        value: Some(value),
+        input: CallInput::new(data),
+        ..Default::default()
+    }
+}
+
+/// Legacy transaction signed with EIP-155 replay protection
+struct LegacyTx {
+    nonce: u64,
+    gas_price: U256,
+    gas_limit: u64,
+    to: Address,
+    value: U256,
+    data: Bytes,
+}
+
+impl LegacyTx {
+    fn fields_length(&self) -> usize {
+        self.nonce.length()
+            + self.gas_price.length()
+            + self.gas_limit.length()
+            + self.to.length()
+            + self.value.length()
+            + self.data.length()
+    }
+
+    fn encode_fields(&self, out: &mut dyn BufMut) {
+        self.nonce.encode(out);
+        self.gas_price.encode(out);
+        self.gas_limit.encode(out);
+        self.to.encode(out);
+        self.value.encode(out);
+        self.data.encode(out);
+    }
+
+    /// RLP of the fields followed by `v`, `r` and `s` (`chain_id`, 0, 0 for the signing hash)
+    fn encode(&self, v: u64, r: U256, s: U256) -> Vec<u8> {
+        let payload_length = self.fields_length() + v.length() + r.length() + s.length();
+        let mut out = Vec::new();
+        Header { list: true, payload_length }.encode(&mut out);
+        self.encode_fields(&mut out);
+        v.encode(&mut out);
+        r.encode(&mut out);
+        s.encode(&mut out);
+        out
+    }
+}
+
+/// Signs and sends a transaction from the wallet, returning its hash once it is mined.
+/// Nonce, gas price and gas limit (estimate plus a margin) are read from the provider
+pub async fn send_transaction(
+    provider: Arc<ChainProvider>,
+    wallet: &LocalWallet,
+    chain_id: u64,
+    to: Address,
+    value: U256,
+    data: Bytes,
+) -> Result<B256> {
+    let from = wallet.address();
+    let nonce = provider.get_transaction_count(from, None).await?.to::<u64>();
+    let gas_price = provider.get_gas_price().await?;
+    let estimate = provider.estimate_gas(call_request(from, to, value, data.clone()), None).await?.to::<u64>();
+    let tx = LegacyTx {
+        nonce,
+        gas_price,
+        gas_limit: estimate + estimate * GAS_MARGIN_PCT / 100,
+        to,
+        value,
+        data,
+    };
+    let signing_hash = keccak256(tx.encode(chain_id, U256::ZERO, U256::ZERO));
+    let signature = wallet.sign_hash(signing_hash).await?;
+    let v = chain_id * 2 + 35 + signature.v().y_parity() as u64;
+    let raw = tx.encode(v, signature.r(), signature.s());
+    let hash = provider.send_raw_transaction(raw.into()).await?;
+    wait_for_receipt(provider, hash).await?;
+    Ok(hash)
+}
+
+/// Polls the receipt of `hash` until the transaction is mined, failing if it reverted
+/// or was not mined after `RECEIPT_MAX_POLLS` polls
+pub async fn wait_for_receipt(provider: Arc<ChainProvider>, hash: B256) -> Result<TransactionReceipt> {
+    let mut ticker = tokio::time::interval(Duration::from_secs(RECEIPT_POLL_INTERVAL));
+    for _ in 0..RECEIPT_MAX_POLLS {
+        ticker.tick().await;
+        if let Some(receipt) = provider.get_transaction_receipt(hash).await? {
+            if receipt.status_code.map_or(false, |s| s.is_zero()) {
+                return Err(eyre!("Transaction {hash} reverted"))
+            }
+            return Ok(receipt)
+        }
+    }
+    Err(eyre!("Transaction {hash} not mined after {}s, it may still be pending", RECEIPT_POLL_INTERVAL * RECEIPT_MAX_POLLS))
+}
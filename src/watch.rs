@@ -0,0 +1,84 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use crate::output::Render;
+use crate::types::RoundData;
+
+/// Why a watched pair is reported
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeReason {
+    /// first answer seen for the pair
+    First,
+    NewRound,
+    /// price moved more than the threshold since the last report
+    PriceMove,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PriceChange {
+    pub reason: ChangeReason,
+    pub previous_price: Option<String>,
+    /// change since the last report, in percent
+    pub change_pct: Option<f64>,
+    #[serde(flatten)]
+    pub round: RoundData,
+}
+
+impl Render for PriceChange {
+    fn text(&self) -> String {
+        let change = match (&self.previous_price, self.change_pct) {
+            (Some(prev), Some(pct)) => format!(" (was {prev}, {pct:+.4}%)"),
+            _ => String::new(),
+        };
+        format!("[{}] {} = {}{} round {}",
+            self.round.updated_at, self.round.pair, self.round.price, change, self.round.round_id
+        )
+    }
+}
+
+/// Keeps the last reported round of each pair and decides what is worth reporting
+#[derive(Debug, Default)]
+pub struct Watcher {
+    /// minimum move in percent, when set only moves above it are reported
+    threshold: Option<f64>,
+    last: HashMap<String, RoundData>,
+}
+
+impl Watcher {
+    pub fn new(threshold: Option<f64>) -> Self {
+        Self { threshold, last: HashMap::new() }
+    }
+
+    /// Compares fresh rounds with the last reported ones, returning the changes to report
+    pub fn update(&mut self, rounds: Vec<RoundData>) -> Vec<PriceChange> {
+        let mut changes = Vec::new();
+        for round in rounds {
+            let change = match self.last.get(&round.pair) {
+                None => Some((ChangeReason::First, None, None)),
+                Some(prev) if prev.round_id == round.round_id => None,
+                Some(prev) => {
+                    let pct = change_pct(prev, &round);
+                    match self.threshold {
+                        None => Some((ChangeReason::NewRound, Some(prev.price.clone()), pct)),
+                        Some(t) if pct.map_or(false, |p| p.abs() >= t) => Some((ChangeReason::PriceMove, Some(prev.price.clone()), pct)),
+                        Some(_) => None,
+                    }
+                },
+            };
+            if let Some((reason, previous_price, change_pct)) = change {
+                self.last.insert(round.pair.clone(), round.clone());
+                changes.push(PriceChange { reason, previous_price, change_pct, round });
+            }
+        }
+        changes
+    }
+}
+
+fn change_pct(prev: &RoundData, current: &RoundData) -> Option<f64> {
+    let prev: f64 = prev.answer.to_string().parse().ok()?;
+    let current: f64 = current.answer.to_string().parse().ok()?;
+    if prev == 0.0 {
+        return None
+    }
+    Some((current - prev) / prev.abs() * 100.0)
+}